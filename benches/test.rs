@@ -357,3 +357,312 @@ fn hashbrown_iter_values(b: &mut Bencher) {
     });
     black_box(map);
 }
+
+#[bench]
+fn smash_insert_tiny(b: &mut Bencher) {
+    b.iter(|| {
+        let mut map = smashmap_new();
+        for i in 0..6 {
+            map.insert(i, 6 - i);
+        }
+        black_box(map);
+    })
+}
+
+#[bench]
+fn smash_small_insert_tiny(b: &mut Bencher) {
+    b.iter(|| {
+        let mut map = smash::SmallHashMap::<i32, i32, 8>::new();
+        for i in 0..6 {
+            map.insert(i, 6 - i);
+        }
+        black_box(map);
+    })
+}
+
+#[bench]
+fn hashbrown_insert_tiny(b: &mut Bencher) {
+    b.iter(|| {
+        let mut map = hashbrown::HashMap::<i32, i32>::new();
+        for i in 0..6 {
+            map.insert(i, 6 - i);
+        }
+        black_box(map);
+    })
+}
+
+// Large enough that the table does not fit in the last level cache
+const LARGE: i32 = 1 << 22;
+
+fn smashmap_large() -> smash::HashMap<i32, i32> {
+    let mut map = smashmap_new();
+    for i in 0..LARGE {
+        map.insert(i, LARGE - i);
+    }
+    map
+}
+
+// Keys in a scattered order, so that consecutive lookups do not share cache lines
+fn lookup_keys(count: usize, max: i32) -> Vec<i32> {
+    (0..count as u32).map(|i| (i.wrapping_mul(2_654_435_761) % max as u32) as i32).collect()
+}
+
+#[bench]
+fn smash_get_loop(b: &mut Bencher) {
+    let mut map = smashmap_new();
+    for i in 0..10000 {
+        map.insert(i, 10000 - i);
+    }
+    let keys = lookup_keys(10000, 20000);
+    b.iter(|| {
+        let vals: Vec<_> = keys.iter().map(|k| map.get(k)).collect();
+        black_box(vals);
+    })
+}
+
+#[bench]
+fn smash_get_many(b: &mut Bencher) {
+    let mut map = smashmap_new();
+    for i in 0..10000 {
+        map.insert(i, 10000 - i);
+    }
+    let keys = lookup_keys(10000, 20000);
+    b.iter(|| black_box(map.get_many(&keys)))
+}
+
+#[bench]
+fn smash_get_loop_large(b: &mut Bencher) {
+    let map = smashmap_large();
+    let keys = lookup_keys(100000, 2 * LARGE);
+    b.iter(|| {
+        let vals: Vec<_> = keys.iter().map(|k| map.get(k)).collect();
+        black_box(vals);
+    })
+}
+
+#[bench]
+fn smash_get_many_large(b: &mut Bencher) {
+    let map = smashmap_large();
+    let keys = lookup_keys(100000, 2 * LARGE);
+    b.iter(|| black_box(map.get_many(&keys)))
+}
+
+#[bench]
+fn smash_contains_loop_large(b: &mut Bencher) {
+    let map = smashmap_large();
+    let keys = lookup_keys(100000, 2 * LARGE);
+    b.iter(|| {
+        let found: Vec<_> = keys.iter().map(|k| map.contains_key(k)).collect();
+        black_box(found);
+    })
+}
+
+#[bench]
+fn smash_contains_many_large(b: &mut Bencher) {
+    let map = smashmap_large();
+    let keys = lookup_keys(100000, 2 * LARGE);
+    b.iter(|| black_box(map.contains_many(&keys)))
+}
+
+#[bench]
+fn smash_insert_loop_large(b: &mut Bencher) {
+    let mut map = smashmap_large();
+    let keys = lookup_keys(100000, LARGE);
+    b.iter(|| {
+        let old: Vec<_> = keys.iter().map(|k| map.insert(*k, 0)).collect();
+        black_box(old);
+    })
+}
+
+#[bench]
+fn smash_insert_many_large(b: &mut Bencher) {
+    let mut map = smashmap_large();
+    let keys = lookup_keys(100000, LARGE);
+    b.iter(|| black_box(map.insert_many(keys.iter().map(|k| (*k, 0)))))
+}
+
+const CLONE_LEN: u64 = 1 << 20;
+
+// Holds the same data as a `u64`, but is not `Copy`, so maps of it are cloned an entry at a time
+#[derive(Clone)]
+struct NotCopy(#[allow(dead_code)] u64);
+
+fn smashmap_clone_source<V>(f: impl Fn(u64) -> V) -> smash::HashMap<u64, V> {
+    let mut map = smash::HashMap::with_capacity(CLONE_LEN as usize);
+    for i in 0..CLONE_LEN {
+        map.insert(i, f(i));
+    }
+    map
+}
+
+#[bench]
+fn smash_clone_copy(b: &mut Bencher) {
+    let map = smashmap_clone_source(|i| i);
+    b.iter(|| black_box(map.clone()))
+}
+
+#[bench]
+fn smash_clone_not_copy(b: &mut Bencher) {
+    let map = smashmap_clone_source(NotCopy);
+    b.iter(|| black_box(map.clone()))
+}
+
+#[bench]
+fn smash_clone_from_copy(b: &mut Bencher) {
+    let map = smashmap_clone_source(|i| i);
+    let mut copy = map.clone();
+    b.iter(|| {
+        copy.clone_from(&map);
+        black_box(&copy);
+    })
+}
+
+#[bench]
+fn smash_clone_from_not_copy(b: &mut Bencher) {
+    let map = smashmap_clone_source(NotCopy);
+    let mut copy = map.clone();
+    b.iter(|| {
+        copy.clone_from(&map);
+        black_box(&copy);
+    })
+}
+
+#[bench]
+fn hashbrown_clone(b: &mut Bencher) {
+    let map = (0..CLONE_LEN).map(|i| (i, i)).collect::<hashbrown::HashMap<u64, u64>>();
+    b.iter(|| black_box(map.clone()))
+}
+
+// The same workloads, for each of `HashMap`'s probing strategies and layouts
+macro_rules! map_benches {
+    ($name:ident, $map:ty) => {
+        mod $name {
+            use super::*;
+
+            fn map_new() -> $map {
+                <$map>::new()
+            }
+
+            #[bench]
+            fn insert(b: &mut Bencher) {
+                b.iter(|| {
+                    let mut map = map_new();
+                    for i in 0..10000 {
+                        map.insert(i, 10000 - i);
+                    }
+                    black_box(map);
+                })
+            }
+
+            #[bench]
+            fn get_in(b: &mut Bencher) {
+                let mut map = map_new();
+                for i in 0..10000 {
+                    map.insert(i, 10000 - i);
+                }
+                b.iter(|| {
+                    for i in 0..10000 {
+                        let val = map.get(&i);
+                        assert_eq!(val, Some(10000 - i).as_ref());
+                        black_box(val);
+                    }
+                })
+            }
+
+            #[bench]
+            fn get_not_in(b: &mut Bencher) {
+                let mut map = map_new();
+                for i in 10000..20000 {
+                    map.insert(i, 10000 - i);
+                }
+                b.iter(|| {
+                    for i in 0..10000 {
+                        let val = map.get(&i);
+                        assert_eq!(val, None);
+                        black_box(val);
+                    }
+                })
+            }
+
+            #[bench]
+            fn remove(b: &mut Bencher) {
+                let mut map = map_new();
+                for i in 0..10000 {
+                    map.insert(i, 10000 - i);
+                }
+                b.iter(|| {
+                    let mut map = map.clone();
+                    for i in 0..10000 {
+                        let val = map.remove(&i);
+                        assert_eq!(val, Some(10000 - i));
+                        black_box(val);
+                    }
+                })
+            }
+
+            #[bench]
+            fn churn(b: &mut Bencher) {
+                let mut map = map_new();
+                for i in 0..10000 {
+                    map.insert(i, 10000 - i);
+                }
+                b.iter(|| {
+                    for i in 0..10000 {
+                        black_box(map.remove(&i));
+                        map.insert(i + 10000, i);
+                    }
+                    for i in 0..10000 {
+                        black_box(map.remove(&(i + 10000)));
+                        map.insert(i, 10000 - i);
+                    }
+                })
+            }
+
+            #[bench]
+            fn iter_keys(b: &mut Bencher) {
+                let mut map = map_new();
+                for i in 0..10000 {
+                    map.insert(i, 10000 - i);
+                }
+                b.iter(|| {
+                    assert_eq!(map.keys().map(|i| *i as u64).sum::<u64>(), (0..10000).sum::<u64>());
+                });
+                black_box(map);
+            }
+
+            #[bench]
+            fn iter_values(b: &mut Bencher) {
+                let mut map = map_new();
+                for i in 0..10000 {
+                    map.insert(i, 10000 - i);
+                }
+                b.iter(|| {
+                    assert_eq!(map.values().map(|i| *i as u64).sum::<u64>(), (0..10000).map(|i| 10000 - i).sum::<u64>());
+                });
+                black_box(map);
+            }
+
+            #[bench]
+            fn insert_tiny(b: &mut Bencher) {
+                b.iter(|| {
+                    let mut map = map_new();
+                    for i in 0..6 {
+                        map.insert(i, 6 - i);
+                    }
+                    black_box(map);
+                })
+            }
+        }
+    };
+}
+
+type SmashMap<P, L = smash::layout::SoA> = smash::HashMap<i32, i32, fxhash::FxBuildHasher, P, L>;
+
+map_benches!(smash_robin_hood, SmashMap<smash::probe::RobinHood>);
+map_benches!(smash_linear, SmashMap<smash::probe::Linear>);
+map_benches!(smash_quadratic, SmashMap<smash::probe::Quadratic>);
+map_benches!(smash_hopscotch, SmashMap<smash::probe::Hopscotch>);
+map_benches!(smash_swiss, SmashMap<smash::probe::Swiss>);
+
+map_benches!(smash_aos, SmashMap<smash::probe::RobinHood, smash::layout::AoS>);
+map_benches!(smash_cache_line, SmashMap<smash::probe::RobinHood, smash::layout::CacheLine>);
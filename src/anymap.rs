@@ -0,0 +1,210 @@
+use std::{
+    any::{Any, TypeId},
+    marker::PhantomData,
+    mem,
+};
+
+use crate::{
+    HashMap,
+    hasher::BuildIdentityHasher,
+};
+
+mod sealed {
+    pub trait Sealed<A: ?Sized> {}
+}
+
+/// Implemented for every type that can be stored in an `AnyMap<A>`, converting it into the boxed trait object `A`.
+pub trait IntoBox<A: ?Sized>: Any + sealed::Sealed<A> {
+    fn into_box(self) -> Box<A>;
+}
+
+impl<T: Any> sealed::Sealed<dyn Any> for T {}
+impl<T: Any> IntoBox<dyn Any> for T {
+    fn into_box(self) -> Box<dyn Any> {
+        Box::new(self)
+    }
+}
+
+impl<T: Any + Send + Sync> sealed::Sealed<dyn Any + Send + Sync> for T {}
+impl<T: Any + Send + Sync> IntoBox<dyn Any + Send + Sync> for T {
+    fn into_box(self) -> Box<dyn Any + Send + Sync> {
+        Box::new(self)
+    }
+}
+
+// `TypeId`s are already hashes, so they are used directly
+type Table<A> = HashMap<TypeId, Box<A>, BuildIdentityHasher>;
+
+// Every value is stored under its own `TypeId`, so the following require that the value really is a `T`
+
+// Takes the boxed value itself, so a `&Box<A>` must be dereferenced first or the box would be taken for the value
+#[inline(always)]
+unsafe fn downcast_ref<A: ?Sized, T>(val: &A) -> &T {
+    &*(val as *const A as *const T)
+}
+
+#[inline(always)]
+unsafe fn downcast_mut<A: ?Sized, T>(val: &mut Box<A>) -> &mut T {
+    &mut *(&mut **val as *mut A as *mut T)
+}
+
+#[inline(always)]
+unsafe fn downcast<A: ?Sized, T>(val: Box<A>) -> T {
+    *Box::from_raw(Box::into_raw(val) as *mut T)
+}
+
+/// A map holding at most one value of each type, keyed by the type itself.
+pub struct AnyMap<A: ?Sized = dyn Any> {
+    table: Table<A>,
+}
+
+/// An `AnyMap` that may only hold values that are `Send` and `Sync`, and so is itself `Send` and `Sync`.
+pub type SendSyncAnyMap = AnyMap<dyn Any + Send + Sync>;
+
+impl<A: ?Sized> AnyMap<A> {
+    pub fn new() -> Self {
+        Self { table: HashMap::new() }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { table: HashMap::with_capacity(capacity) }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.table.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+
+    pub fn contains<T: IntoBox<A>>(&self) -> bool {
+        self.table.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Insert a value, returning the previous value of the same type.
+    pub fn insert<T: IntoBox<A>>(&mut self, val: T) -> Option<T> {
+        self.table
+            .insert(TypeId::of::<T>(), val.into_box())
+            .map(|old| unsafe { downcast(old) })
+    }
+
+    pub fn get<T: IntoBox<A>>(&self) -> Option<&T> {
+        self.table
+            .get(&TypeId::of::<T>())
+            .map(|val| unsafe { downcast_ref(&**val) })
+    }
+
+    pub fn get_mut<T: IntoBox<A>>(&mut self) -> Option<&mut T> {
+        self.table
+            .get_mut(&TypeId::of::<T>())
+            .map(|val| unsafe { downcast_mut(val) })
+    }
+
+    pub fn remove<T: IntoBox<A>>(&mut self) -> Option<T> {
+        self.table
+            .remove(&TypeId::of::<T>())
+            .map(|val| unsafe { downcast(val) })
+    }
+
+    pub fn entry<T: IntoBox<A>>(&mut self) -> Entry<'_, A, T> {
+        let key = TypeId::of::<T>();
+
+        let hash = Table::<A>::hash_of(&key, self.table.hasher());
+        let mut hint = match self.table.probe_idx(hash, |k| k.eq(&key)) {
+            Ok(idx) => return Entry::Occupied(OccupiedEntry { table: &mut self.table, idx, _phantom: PhantomData }),
+            Err(hint) => hint,
+        };
+
+        // Only a vacant entry needs room, and growing moves the slots, so the hint must then be found again
+        if self.table.try_grow() {
+            hint = self.table.probe_idx(hash, |k| k.eq(&key)).unwrap_err();
+        }
+        Entry::Vacant(VacantEntry { table: &mut self.table, hint, hash, _phantom: PhantomData })
+    }
+}
+
+impl<A: ?Sized> Default for AnyMap<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub enum Entry<'a, A: ?Sized, T> {
+    Occupied(OccupiedEntry<'a, A, T>),
+    Vacant(VacantEntry<'a, A, T>),
+}
+
+impl<'a, A: ?Sized, T: IntoBox<A>> Entry<'a, A, T> {
+    pub fn or_insert(self, default: T) -> &'a mut T {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> T>(self, default: F) -> &'a mut T {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut T where T: Default {
+        self.or_insert_with(T::default)
+    }
+
+    pub fn and_modify<F: FnOnce(&mut T)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+pub struct OccupiedEntry<'a, A: ?Sized, T> {
+    table: &'a mut Table<A>,
+    idx: usize,
+    _phantom: PhantomData<T>,
+}
+
+impl<'a, A: ?Sized, T: IntoBox<A>> OccupiedEntry<'a, A, T> {
+    pub fn get(&self) -> &T {
+        unsafe { downcast_ref(&**self.table.val_ref(self.idx)) }
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { downcast_mut(self.table.val_mut(self.idx)) }
+    }
+
+    pub fn into_mut(self) -> &'a mut T {
+        unsafe { downcast_mut(self.table.val_mut(self.idx)) }
+    }
+
+    pub fn insert(&mut self, val: T) -> T {
+        mem::replace(self.get_mut(), val)
+    }
+
+    pub fn remove(self) -> T {
+        unsafe { downcast(self.table.remove_idx(self.idx).1) }
+    }
+}
+
+pub struct VacantEntry<'a, A: ?Sized, T> {
+    table: &'a mut Table<A>,
+    hint: usize,
+    hash: u64,
+    _phantom: PhantomData<T>,
+}
+
+impl<'a, A: ?Sized, T: IntoBox<A>> VacantEntry<'a, A, T> {
+    pub fn insert(self, val: T) -> &'a mut T {
+        let idx = self.table.insert_at(self.hint, self.hash, TypeId::of::<T>(), val.into_box());
+        unsafe { downcast_mut(self.table.val_mut(idx)) }
+    }
+}
@@ -0,0 +1,291 @@
+use std::{
+    error::Error,
+    fmt,
+    hash::{BuildHasher, BuildHasherDefault, Hash},
+    mem::{self, MaybeUninit},
+    ptr,
+};
+
+use fxhash::FxBuildHasher;
+
+use crate::{
+    probe::Slots,
+    robin_hood,
+};
+
+/// The error returned when inserting a new key into a full `ArrayHashMap`. It hands back the rejected entry.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CapacityFull<K, V>(pub K, pub V);
+
+impl<K, V> fmt::Display for CapacityFull<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "map is at capacity")
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> Error for CapacityFull<K, V> {}
+
+struct ArraySlots<K, V, const N: usize> {
+    entries: [MaybeUninit<(K, V)>; N],
+    occupied: [bool; N],
+}
+
+impl<K, V, const N: usize> ArraySlots<K, V, N> {
+    const fn new() -> Self {
+        Self {
+            // An array of `MaybeUninit` needs no initialisation
+            entries: unsafe { MaybeUninit::uninit().assume_init() },
+            occupied: [false; N],
+        }
+    }
+
+    // The following require that `idx` is occupied
+
+    #[inline(always)]
+    unsafe fn get_ref(&self, idx: usize) -> &(K, V) {
+        &*self.entries[idx].as_ptr()
+    }
+
+    #[inline(always)]
+    unsafe fn get_mut(&mut self, idx: usize) -> &mut (K, V) {
+        &mut *self.entries[idx].as_mut_ptr()
+    }
+}
+
+impl<K, V, const N: usize> Slots for ArraySlots<K, V, N> {
+    type Key = K;
+    type Val = V;
+
+    #[inline(always)]
+    fn cap(&self) -> usize {
+        N
+    }
+
+    #[inline(always)]
+    fn key(&self, idx: usize) -> Option<&K> {
+        if self.occupied[idx] {
+            Some(unsafe { &self.get_ref(idx).0 })
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn take(&mut self, idx: usize) -> (K, V) {
+        self.occupied[idx] = false;
+        ptr::read(self.entries[idx].as_ptr())
+    }
+
+    #[inline(always)]
+    unsafe fn put(&mut self, idx: usize, key: K, val: V) {
+        self.entries[idx] = MaybeUninit::new((key, val));
+        self.occupied[idx] = true;
+    }
+
+    #[inline(always)]
+    unsafe fn swap(&mut self, idx: usize, key: &mut K, val: &mut V) {
+        let entry = self.get_mut(idx);
+        mem::swap(key, &mut entry.0);
+        mem::swap(val, &mut entry.1);
+    }
+
+    #[inline(always)]
+    unsafe fn shift(&mut self, from: usize, to: usize) {
+        ptr::copy_nonoverlapping(self.entries[from].as_ptr(), self.entries[to].as_mut_ptr(), 1);
+        self.occupied[from] = false;
+        self.occupied[to] = true;
+    }
+}
+
+/// A map with a fixed capacity of `N` entries, stored inline. It never allocates, so it may live in a `static` or be
+/// used where no allocator is available. Lookups and probing behave exactly as they do for `HashMap`.
+///
+/// `N` must be a power of two. Inserting a new key into a full map fails with `CapacityFull`.
+pub struct ArrayHashMap<K: Hash + Eq, V, const N: usize, S: BuildHasher = FxBuildHasher> {
+    slots: ArraySlots<K, V, N>,
+    len: usize,
+    hasher: S,
+}
+
+impl<K: Hash + Eq, V, const N: usize> ArrayHashMap<K, V, N> {
+    pub const fn new() -> Self {
+        Self::with_hasher(BuildHasherDefault::new())
+    }
+}
+
+impl<K: Hash + Eq, V, const N: usize, S: BuildHasher> ArrayHashMap<K, V, N, S> {
+    // Private interface
+
+    #[inline(always)]
+    fn probe_idx(&self, key: &K) -> Result<usize, usize> {
+        let hasher = &self.hasher;
+        robin_hood::probe(&self.slots, hasher.hash_one(key), |k| k.eq(key), |k| hasher.hash_one(k))
+    }
+
+    #[inline(always)]
+    fn get_idx(&self, key: &K) -> Option<usize> {
+        if self.len == 0 {
+            None
+        } else {
+            self.probe_idx(key).ok()
+        }
+    }
+
+    // Public interface
+
+    pub const fn with_hasher(hasher: S) -> Self {
+        assert!(N.is_power_of_two(), "ArrayHashMap capacity must be a power of two");
+
+        Self {
+            slots: ArraySlots::new(),
+            len: 0,
+            hasher,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    pub fn clear(&mut self) {
+        for idx in 0..N {
+            if self.slots.occupied[idx] {
+                drop(unsafe { self.slots.take(idx) });
+            }
+        }
+        self.len = 0;
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.get_idx(key).map(|idx| unsafe { &self.slots.get_ref(idx).1 })
+    }
+
+    pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
+        self.get_idx(key).map(|idx| {
+            let (k, v) = unsafe { self.slots.get_ref(idx) };
+            (k, v)
+        })
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get_idx(key).is_some()
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        match self.get_idx(key) {
+            Some(idx) => Some(unsafe { &mut self.slots.get_mut(idx).1 }),
+            None => None,
+        }
+    }
+
+    /// Insert an entry, returning the previous value. Replacing the value of an existing key always succeeds, even when
+    /// the map is full.
+    pub fn insert(&mut self, key: K, val: V) -> Result<Option<V>, CapacityFull<K, V>> {
+        match self.probe_idx(&key) {
+            Ok(idx) => Ok(Some(mem::replace(unsafe { &mut self.slots.get_mut(idx).1 }, val))),
+            Err(_) if self.len == N => Err(CapacityFull(key, val)),
+            Err(idx) => {
+                let hasher = &self.hasher;
+                robin_hood::insert_at(&mut self.slots, idx, key, val, |k| hasher.hash_one(k));
+                self.len += 1;
+                Ok(None)
+            },
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    pub fn remove_entry(&mut self, key: &K) -> Option<(K, V)> {
+        let idx = self.get_idx(key)?;
+        let hasher = &self.hasher;
+        self.len -= 1;
+        Some(robin_hood::remove_at(&mut self.slots, idx, |k| hasher.hash_one(k)))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.iter().map(|(_, v)| v)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> + '_ {
+        self.iter_mut().map(|(_, v)| v)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.slots.entries
+            .iter()
+            .zip(self.slots.occupied.iter())
+            .filter(|(_, occupied)| **occupied)
+            .map(|(entry, _)| {
+                let (k, v) = unsafe { &*entry.as_ptr() };
+                (k, v)
+            })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> + '_ {
+        self.slots.entries
+            .iter_mut()
+            .zip(self.slots.occupied.iter())
+            .filter(|(_, occupied)| **occupied)
+            .map(|(entry, _)| {
+                let (k, v) = unsafe { &mut *entry.as_mut_ptr() };
+                (&*k, v)
+            })
+    }
+}
+
+impl<K: Hash + Eq, V, const N: usize, S: BuildHasher> Drop for ArrayHashMap<K, V, N, S> {
+    fn drop(&mut self) {
+        for idx in 0..N {
+            if self.slots.occupied[idx] {
+                unsafe { ptr::drop_in_place(self.slots.entries[idx].as_mut_ptr()) };
+            }
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, const N: usize, S: BuildHasher + Default> Default for ArrayHashMap<K, V, N, S> {
+    fn default() -> Self {
+        Self::with_hasher(Default::default())
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone, const N: usize, S: BuildHasher + Clone> Clone for ArrayHashMap<K, V, N, S> {
+    fn clone(&self) -> Self {
+        // Entries keep their positions, so the probe sequences stay intact
+        let mut slots = ArraySlots::new();
+        for idx in 0..N {
+            if self.slots.occupied[idx] {
+                let (k, v) = unsafe { self.slots.get_ref(idx) };
+                unsafe { slots.put(idx, k.clone(), v.clone()) };
+            }
+        }
+
+        Self {
+            slots,
+            len: self.len,
+            hasher: self.hasher.clone(),
+        }
+    }
+}
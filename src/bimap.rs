@@ -0,0 +1,218 @@
+use std::{
+    hash::{BuildHasher, Hash},
+    slice,
+};
+
+use fxhash::FxBuildHasher;
+
+use crate::{
+    HashMap,
+    hasher::{BuildIdentityHasher, Hashed},
+};
+
+// Each index table maps the hash of one side of a pair to the position of the pair in storage
+type Index = HashMap<Hashed<usize>, (), BuildIdentityHasher>;
+
+/// The pairs displaced by `BiMap::insert`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Overwritten<L, R> {
+    /// Neither value was present.
+    Neither,
+    /// The left value was paired with another right value.
+    Left(L, R),
+    /// The right value was paired with another left value.
+    Right(L, R),
+    /// The exact pair was already present.
+    Pair(L, R),
+    /// Both values were present in different pairs: the pair containing the left value, then the pair containing the
+    /// right value.
+    Both((L, R), (L, R)),
+}
+
+/// A one-to-one map that can be queried in either direction. Each pair is stored once, and two hash tables index it
+/// by its left and right values.
+pub struct BiMap<L: Hash + Eq, R: Hash + Eq, S: BuildHasher + Default = FxBuildHasher> {
+    pairs: Vec<(L, R)>,
+    left: Index,
+    right: Index,
+
+    hasher: S,
+}
+
+impl<L: Hash + Eq, R: Hash + Eq, S: BuildHasher + Default> BiMap<L, R, S> {
+    // Private interface
+
+    #[inline(always)]
+    fn hash<T: Hash>(hasher: &S, val: &T) -> u64 {
+        hasher.hash_one(val)
+    }
+
+    #[inline(always)]
+    fn find(index: &Index, hash: u64, mut eq: impl FnMut(usize) -> bool) -> Option<usize> {
+        index
+            .find_idx(hash, |slot| slot.hash == hash && eq(slot.val))
+            .map(|i| index.key(i).unwrap().val)
+    }
+
+    #[inline(always)]
+    fn find_left(&self, left: &L) -> Option<usize> {
+        let pairs = &self.pairs;
+        Self::find(&self.left, Self::hash(&self.hasher, left), |idx| pairs[idx].0 == *left)
+    }
+
+    #[inline(always)]
+    fn find_right(&self, right: &R) -> Option<usize> {
+        let pairs = &self.pairs;
+        Self::find(&self.right, Self::hash(&self.hasher, right), |idx| pairs[idx].1 == *right)
+    }
+
+    // Point the slot that refers to `from` at `to` instead
+    fn relink(index: &mut Index, hash: u64, from: usize, to: usize) {
+        let i = index.get_idx(&Hashed { hash, val: from }).unwrap();
+        unsafe { index.key_mut(i) }.val = to;
+    }
+
+    fn remove_pair(&mut self, idx: usize) -> (L, R) {
+        let (left, right) = &self.pairs[idx];
+        self.left.remove(&Hashed { hash: Self::hash(&self.hasher, left), val: idx });
+        self.right.remove(&Hashed { hash: Self::hash(&self.hasher, right), val: idx });
+
+        // The last pair is about to be moved into `idx`
+        let last = self.pairs.len() - 1;
+        if idx != last {
+            let (left, right) = &self.pairs[last];
+            Self::relink(&mut self.left, Self::hash(&self.hasher, left), last, idx);
+            Self::relink(&mut self.right, Self::hash(&self.hasher, right), last, idx);
+        }
+
+        self.pairs.swap_remove(idx)
+    }
+
+    // Public interface
+
+    pub fn new() -> Self {
+        Self::with_capacity_and_hasher(0, Default::default())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, Default::default())
+    }
+
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self {
+            pairs: Vec::with_capacity(capacity),
+            left: HashMap::with_capacity(capacity),
+            right: HashMap::with_capacity(capacity),
+
+            hasher,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.pairs.clear();
+        self.left.clear();
+        self.right.clear();
+    }
+
+    /// Insert a pair, removing any existing pairs that contain either value.
+    pub fn insert(&mut self, left: L, right: R) -> Overwritten<L, R> {
+        let overwritten = match (self.find_left(&left), self.find_right(&right)) {
+            (None, None) => Overwritten::Neither,
+            (Some(l), Some(r)) if l == r => {
+                let (l, r) = self.remove_pair(l);
+                Overwritten::Pair(l, r)
+            },
+            (Some(l), Some(r)) => {
+                // Remove the later pair first so that the earlier one is not moved by it
+                if l > r {
+                    let left_pair = self.remove_pair(l);
+                    Overwritten::Both(left_pair, self.remove_pair(r))
+                } else {
+                    let right_pair = self.remove_pair(r);
+                    Overwritten::Both(self.remove_pair(l), right_pair)
+                }
+            },
+            (Some(l), None) => {
+                let (l, r) = self.remove_pair(l);
+                Overwritten::Left(l, r)
+            },
+            (None, Some(r)) => {
+                let (l, r) = self.remove_pair(r);
+                Overwritten::Right(l, r)
+            },
+        };
+
+        let idx = self.pairs.len();
+        self.left.insert(Hashed { hash: Self::hash(&self.hasher, &left), val: idx }, ());
+        self.right.insert(Hashed { hash: Self::hash(&self.hasher, &right), val: idx }, ());
+        self.pairs.push((left, right));
+
+        overwritten
+    }
+
+    pub fn get_by_left(&self, left: &L) -> Option<&R> {
+        self.find_left(left).map(|idx| &self.pairs[idx].1)
+    }
+
+    pub fn get_by_right(&self, right: &R) -> Option<&L> {
+        self.find_right(right).map(|idx| &self.pairs[idx].0)
+    }
+
+    pub fn contains_left(&self, left: &L) -> bool {
+        self.find_left(left).is_some()
+    }
+
+    pub fn contains_right(&self, right: &R) -> bool {
+        self.find_right(right).is_some()
+    }
+
+    pub fn remove_by_left(&mut self, left: &L) -> Option<(L, R)> {
+        self.find_left(left).map(|idx| self.remove_pair(idx))
+    }
+
+    pub fn remove_by_right(&mut self, right: &R) -> Option<(L, R)> {
+        self.find_right(right).map(|idx| self.remove_pair(idx))
+    }
+
+    pub fn iter(&self) -> Iter<'_, L, R> {
+        Iter { inner: self.pairs.iter() }
+    }
+
+    pub fn left_values(&self) -> impl Iterator<Item = &L> + '_ {
+        self.pairs.iter().map(|(l, _)| l)
+    }
+
+    pub fn right_values(&self) -> impl Iterator<Item = &R> + '_ {
+        self.pairs.iter().map(|(_, r)| r)
+    }
+}
+
+impl<L: Hash + Eq, R: Hash + Eq, S: BuildHasher + Default> Default for BiMap<L, R, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Iter<'a, L: 'a, R: 'a> {
+    inner: slice::Iter<'a, (L, R)>,
+}
+
+impl<'a, L: 'a, R: 'a> Iterator for Iter<'a, L, R> {
+    type Item = (&'a L, &'a R);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(l, r)| (l, r))
+    }
+}
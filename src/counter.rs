@@ -0,0 +1,161 @@
+use std::{
+    cmp::Reverse,
+    hash::{BuildHasher, Hash},
+    iter::FromIterator,
+};
+
+use fxhash::FxBuildHasher;
+
+use crate::HashMap;
+
+/// A map from keys to the number of times they have been seen.
+pub struct Counter<K: Hash + Eq, S: BuildHasher + Default = FxBuildHasher> {
+    map: HashMap<K, u64, S>,
+    total: u64,
+}
+
+impl<K: Hash + Eq, S: BuildHasher + Default> Counter<K, S> {
+    pub fn new() -> Self {
+        Self {
+            map: HashMap::new(),
+            total: 0,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, Default::default())
+    }
+
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self {
+            map: HashMap::with_capacity_and_hasher(capacity, hasher),
+            total: 0,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        self.map.hasher()
+    }
+
+    /// The number of distinct keys.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// The sum of all counts.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.total = 0;
+    }
+
+    /// Count `key` once, returning its new count.
+    pub fn add(&mut self, key: K) -> u64 {
+        self.add_n(key, 1)
+    }
+
+    /// Count `key` `n` times, returning its new count.
+    pub fn add_n(&mut self, key: K, n: u64) -> u64 {
+        let count = self.map.get_or_insert_with(key, || 0);
+        *count += n;
+        self.total += n;
+        *count
+    }
+
+    /// Reduce the count for `key` by up to `n`, returning its new count. Keys whose count reaches zero are removed.
+    pub fn subtract(&mut self, key: &K, n: u64) -> u64 {
+        let idx = match self.map.get_idx(key) {
+            Some(idx) => idx,
+            None => return 0,
+        };
+
+        let count = unsafe { self.map.val_mut(idx) };
+        let n = n.min(*count);
+        *count -= n;
+        self.total -= n;
+
+        match *count {
+            0 => {
+                self.map.remove_idx(idx);
+                0
+            },
+            count => count,
+        }
+    }
+
+    /// The count for `key`, which is zero if it has never been seen.
+    pub fn get(&self, key: &K) -> u64 {
+        self.map.get(key).copied().unwrap_or(0)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Forget `key`, returning the count it had.
+    pub fn remove(&mut self, key: &K) -> u64 {
+        let count = self.map.remove(key).unwrap_or(0);
+        self.total -= count;
+        count
+    }
+
+    /// The `k` keys with the highest counts, most common first. Ties are broken arbitrarily.
+    pub fn most_common(&self, k: usize) -> Vec<(&K, u64)> {
+        let mut counts = self.map.iter().map(|(key, count)| (key, *count)).collect::<Vec<_>>();
+
+        // Only the top `k` need to be ordered
+        if k < counts.len() {
+            if k > 0 {
+                counts.select_nth_unstable_by_key(k - 1, |(_, count)| Reverse(*count));
+            }
+            counts.truncate(k);
+        }
+        counts.sort_unstable_by_key(|(_, count)| Reverse(*count));
+
+        counts
+    }
+
+    /// Add every count in `other` to this counter.
+    pub fn merge<S2: BuildHasher + Default>(&mut self, other: &Counter<K, S2>) where K: Clone {
+        for (key, count) in other.iter() {
+            self.add_n(key.clone(), count);
+        }
+    }
+
+    pub fn keys(&self) -> crate::Keys<'_, K, u64> {
+        self.map.keys()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, u64)> + '_ {
+        self.map.iter().map(|(key, count)| (key, *count))
+    }
+}
+
+impl<K: Hash + Eq, S: BuildHasher + Default> Default for Counter<K, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq, S: BuildHasher + Default> Extend<K> for Counter<K, S> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        for key in iter {
+            self.add(key);
+        }
+    }
+}
+
+impl<K: Hash + Eq, S: BuildHasher + Default> FromIterator<K> for Counter<K, S> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut counter = Self::new();
+        counter.extend(iter);
+        counter
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    hash::{BuildHasher, Hash},
+    sync::Arc,
+};
+
+use fxhash::FxBuildHasher;
+
+use crate::HashMap;
+
+// The number of entries above which a shard is split in two. This bounds how much must be copied when a shared shard
+// is first written to.
+const SHARD_LEN: usize = 1024;
+
+struct Shard<K, V, S> {
+    map: HashMap<K, V, S>,
+    // The number of leading hash bits that every key in the shard has in common
+    depth: u32,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone, S: BuildHasher + Clone> Clone for Shard<K, V, S> {
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+            depth: self.depth,
+        }
+    }
+}
+
+/// A map that can be cloned in constant time. Clones share their entries until one of them is modified, at which point
+/// only the part of the table being modified is copied.
+///
+/// Entries are spread over a number of shards by the top bits of their hashes, and each shard is shared independently.
+/// A shard is split in two when it grows too large, so a single change to a clone of a large map copies only a small
+/// fraction of it.
+pub struct CowHashMap<K: Hash + Eq + Clone, V: Clone, S: BuildHasher + Clone + Default = FxBuildHasher> {
+    shards: Arc<Vec<Arc<Shard<K, V, S>>>>,
+    // Maps the top `bits` bits of a hash to the shard holding it. A shard with a smaller depth has several entries.
+    dir: Arc<Vec<usize>>,
+    bits: u32,
+    len: usize,
+
+    hasher: S,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone, S: BuildHasher + Clone + Default> CowHashMap<K, V, S> {
+    // Private interface
+
+    #[inline(always)]
+    fn hash_of(&self, key: &K) -> u64 {
+        self.hasher.hash_one(key)
+    }
+
+    #[inline(always)]
+    fn prefix(hash: u64, bits: u32) -> usize {
+        if bits == 0 {
+            0
+        } else {
+            (hash >> (64 - bits)) as usize
+        }
+    }
+
+    #[inline(always)]
+    fn shard_for(&self, hash: u64) -> usize {
+        self.dir[Self::prefix(hash, self.bits)]
+    }
+
+    #[inline(always)]
+    fn find(&self, key: &K) -> Option<(usize, usize)> {
+        let hash = self.hash_of(key);
+        let shard = self.shard_for(hash);
+        self.shards[shard].map.find_idx(hash, |k| k.eq(key)).map(|idx| (shard, idx))
+    }
+
+    // Get a unique reference to a shard, copying it (and the list of shards) if they are shared
+    #[inline(always)]
+    fn shard_mut(&mut self, shard: usize) -> &mut Shard<K, V, S> {
+        Arc::make_mut(&mut Arc::make_mut(&mut self.shards)[shard])
+    }
+
+    // Divide a shard in two by the next bit of its keys' hashes, leaving every other shard untouched
+    #[cold]
+    fn split(&mut self, shard: usize) {
+        let depth = self.shards[shard].depth + 1;
+        if depth > self.bits {
+            // The shard has only one directory entry, so the directory must be doubled to give each half its own
+            self.dir = Arc::new((0..self.dir.len() * 2).map(|i| self.dir[i >> 1]).collect());
+            self.bits += 1;
+        }
+
+        // The shard was just made unique by the insertion that filled it, so its entries are moved rather than copied
+        let entries = self.shard_mut(shard).map.take_entries();
+        let mut lo = HashMap::with_capacity_and_hasher(entries.len() / 2, self.hasher.clone());
+        let mut hi = HashMap::with_capacity_and_hasher(entries.len() / 2, self.hasher.clone());
+        for (key, val) in entries {
+            let hash = self.hash_of(&key);
+            if Self::prefix(hash, depth) & 1 == 0 {
+                lo.insert_hashed(hash, key, val);
+            } else {
+                hi.insert_hashed(hash, key, val);
+            }
+        }
+
+        let shards = Arc::make_mut(&mut self.shards);
+        shards[shard] = Arc::new(Shard { map: lo, depth });
+        shards.push(Arc::new(Shard { map: hi, depth }));
+        let hi = shards.len() - 1;
+
+        // Half of the shard's directory entries now belong to the new shard
+        let shift = self.bits - depth;
+        for (prefix, entry) in Arc::make_mut(&mut self.dir).iter_mut().enumerate() {
+            if *entry == shard && (prefix >> shift) & 1 == 1 {
+                *entry = hi;
+            }
+        }
+    }
+
+    // Public interface
+
+    pub fn new() -> Self {
+        Self::with_hasher(Default::default())
+    }
+
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            shards: Arc::new(vec![Arc::new(Shard {
+                map: HashMap::with_capacity_and_hasher(0, hasher.clone()),
+                depth: 0,
+            })]),
+            dir: Arc::new(vec![0]),
+            bits: 0,
+            len: 0,
+
+            hasher,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the two maps still share all of their entries, such that neither has been modified since one was cloned
+    /// from the other.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.shards, &other.shards)
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::with_hasher(self.hasher.clone());
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.find(key).map(|(shard, idx)| unsafe { self.shards[shard].map.val_ref(idx) })
+    }
+
+    pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
+        self.find(key).map(|(shard, idx)| {
+            let shard = &self.shards[shard].map;
+            unsafe { (shard.key(idx).unwrap(), shard.val_ref(idx)) }
+        })
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.find(key).is_some()
+    }
+
+    /// Get a mutable reference to a value, first copying its shard if it is shared with another map.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        // Entries keep their positions when a shard is copied
+        let (shard, idx) = self.find(key)?;
+        Some(unsafe { self.shard_mut(shard).map.val_mut(idx) })
+    }
+
+    pub fn insert(&mut self, key: K, val: V) -> Option<V> {
+        let hash = self.hash_of(&key);
+        let shard = self.shard_for(hash);
+        let old = self.shard_mut(shard).map.insert_hashed(hash, key, val);
+
+        if old.is_none() {
+            self.len += 1;
+            // The directory is only doubled while it is small next to the map, so that keys sharing a hash cannot make
+            // it grow without limit
+            let Shard { map, depth } = &*self.shards[shard];
+            if map.len() > SHARD_LEN && (*depth < self.bits || self.dir.len() * SHARD_LEN < self.len * 2) {
+                self.split(shard);
+            }
+        }
+        old
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    pub fn remove_entry(&mut self, key: &K) -> Option<(K, V)> {
+        // Only copy the shard if there is something to remove from it
+        let (shard, idx) = self.find(key)?;
+        self.len -= 1;
+        Some(self.shard_mut(shard).map.remove_idx(idx))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.iter().map(|(_, v)| v)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.shards.iter().flat_map(|shard| shard.map.iter())
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone, S: BuildHasher + Clone + Default> Clone for CowHashMap<K, V, S> {
+    fn clone(&self) -> Self {
+        Self {
+            shards: self.shards.clone(),
+            dir: self.dir.clone(),
+            bits: self.bits,
+            len: self.len,
+
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone, S: BuildHasher + Clone + Default> Default for CowHashMap<K, V, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
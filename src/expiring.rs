@@ -0,0 +1,359 @@
+use std::{
+    hash::{BuildHasher, Hash},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use fxhash::FxBuildHasher;
+
+use crate::HashMap;
+
+// Timer wheel parameters
+const WHEEL_SLOTS: usize = 256;
+const WHEEL_RESOLUTION_NANOS: u64 = 100_000_000; // 100ms
+
+/// A source of time for `ExpiringMap`, measured as a `Duration` since an arbitrary (but fixed) origin.
+pub trait Clock {
+    fn now(&self) -> Duration;
+}
+
+/// A `Clock` that follows the system's monotonic clock.
+#[derive(Copy, Clone, Debug)]
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self { origin: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// A `Clock` that only moves when told to. Clones share the same time, so a test can keep a handle to the clock
+/// that it gave to a map.
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    nanos: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.nanos.fetch_add(by.as_nanos() as u64, Ordering::SeqCst);
+    }
+
+    pub fn set(&self, now: Duration) {
+        self.nanos.store(now.as_nanos() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
+
+// Marks the end of a timer list
+const NIL: usize = usize::MAX;
+
+// A timer in the wheel, linked into the list of timers that share its slot
+struct Timer<K> {
+    key: K,
+    deadline: Duration,
+    prev: usize,
+    next: usize,
+}
+
+// A hashed timer wheel. Each timer records the key it belongs to and that entry's deadline, and is cancelled whenever
+// the entry is removed or replaced, so every timer in the wheel belongs to an entry in the map. Timers live in a slab,
+// where the index of each is the token that cancels it.
+struct TimerWheel<K> {
+    timers: Vec<Option<Timer<K>>>,
+    free: Vec<usize>, // The vacant indices of `timers`
+    heads: Vec<usize>, // For each slot, the first timer in its list
+    tick: u64, // The last tick that was advanced to
+}
+
+impl<K> TimerWheel<K> {
+    fn new() -> Self {
+        Self {
+            timers: Vec::new(),
+            free: Vec::new(),
+            heads: vec![NIL; WHEEL_SLOTS],
+            tick: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn tick_for(time: Duration) -> u64 {
+        (time.as_nanos() / WHEEL_RESOLUTION_NANOS as u128) as u64
+    }
+
+    #[inline(always)]
+    fn slot_for(deadline: Duration) -> usize {
+        (Self::tick_for(deadline) % WHEEL_SLOTS as u64) as usize
+    }
+
+    #[inline(always)]
+    fn timer(&self, token: usize) -> &Timer<K> {
+        self.timers[token].as_ref().unwrap()
+    }
+
+    #[inline(always)]
+    fn timer_mut(&mut self, token: usize) -> &mut Timer<K> {
+        self.timers[token].as_mut().unwrap()
+    }
+
+    // Schedule a timer, returning the token that cancels it
+    fn schedule(&mut self, key: K, deadline: Duration) -> usize {
+        let slot = Self::slot_for(deadline);
+        let next = self.heads[slot];
+        let timer = Some(Timer { key, deadline, prev: NIL, next });
+        let token = match self.free.pop() {
+            Some(token) => {
+                self.timers[token] = timer;
+                token
+            },
+            None => {
+                self.timers.push(timer);
+                self.timers.len() - 1
+            },
+        };
+
+        if next != NIL {
+            self.timer_mut(next).prev = token;
+        }
+        self.heads[slot] = token;
+        token
+    }
+
+    // Remove a timer from the wheel, returning its key
+    fn cancel(&mut self, token: usize) -> K {
+        let timer = self.timers[token].take().unwrap();
+        self.free.push(token);
+
+        if timer.prev == NIL {
+            self.heads[Self::slot_for(timer.deadline)] = timer.next;
+        } else {
+            self.timer_mut(timer.prev).next = timer.next;
+        }
+        if timer.next != NIL {
+            self.timer_mut(timer.next).prev = timer.prev;
+        }
+        timer.key
+    }
+
+    // Remove every timer that is due at or before `now`, passing its key to `f`
+    fn advance(&mut self, now: Duration, mut f: impl FnMut(K)) {
+        let now_tick = Self::tick_for(now);
+        let ticks = (now_tick.saturating_sub(self.tick) + 1).min(WHEEL_SLOTS as u64);
+
+        for tick in self.tick..self.tick + ticks {
+            let mut token = self.heads[(tick % WHEEL_SLOTS as u64) as usize];
+            while token != NIL {
+                let timer = self.timer(token);
+                let next = timer.next;
+                // Timers due on a later turn of the wheel stay where they are
+                if timer.deadline <= now {
+                    f(self.cancel(token));
+                }
+                token = next;
+            }
+        }
+
+        self.tick = self.tick.max(now_tick);
+    }
+
+    fn clear(&mut self) {
+        self.timers.clear();
+        self.free.clear();
+        self.heads.iter_mut().for_each(|head| *head = NIL);
+    }
+}
+
+struct Expiring<V> {
+    val: V,
+    deadline: Duration,
+    timer: usize, // The token of the entry's timer
+}
+
+/// A map in which every entry has a time-to-live. Expired entries are invisible to lookups and are reclaimed either
+/// lazily, when a mutating operation probes them, or in bulk by `purge_expired`.
+pub struct ExpiringMap<K: Hash + Eq + Clone, V, C: Clock = SystemClock, S: BuildHasher + Default = FxBuildHasher> {
+    map: HashMap<K, Expiring<V>, S>,
+    wheel: TimerWheel<K>,
+    clock: C,
+}
+
+impl<K: Hash + Eq + Clone, V> ExpiringMap<K, V> {
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock::new())
+    }
+}
+
+impl<K: Hash + Eq + Clone, V, C: Clock, S: BuildHasher + Default> ExpiringMap<K, V, C, S> {
+    pub fn with_clock(clock: C) -> Self {
+        Self::with_clock_and_hasher(clock, Default::default())
+    }
+
+    pub fn with_clock_and_hasher(clock: C, hasher: S) -> Self {
+        Self {
+            map: HashMap::with_capacity_and_hasher(0, hasher),
+            wheel: TimerWheel::new(),
+            clock,
+        }
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// The number of entries held by the map, including expired entries that have not yet been reclaimed.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.wheel.clear();
+    }
+
+    /// Insert a value that expires `ttl` from now, returning the previous value if it had not yet expired.
+    pub fn insert(&mut self, key: K, val: V, ttl: Duration) -> Option<V> {
+        let now = self.clock.now();
+        let deadline = now + ttl;
+        let hash = HashMap::<K, Expiring<V>, S>::hash_of(&key, &self.map.hasher);
+
+        // The previous entry for the key, if any, has not expired once its probe sequence has been reclaimed
+        self.reclaim_probe(hash, now);
+        let timer = self.wheel.schedule(key.clone(), deadline);
+        let old = self.map.insert_hashed(hash, key, Expiring { val, deadline, timer })?;
+        self.wheel.cancel(old.timer);
+        Some(old.val)
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let now = self.clock.now();
+        self.map
+            .get(key)
+            .filter(|e| e.deadline > now)
+            .map(|e| &e.val)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let idx = self.live_idx(key)?;
+        Some(unsafe { &mut self.map.val_mut(idx).val })
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// The time remaining before the entry for `key` expires.
+    pub fn ttl(&self, key: &K) -> Option<Duration> {
+        let now = self.clock.now();
+        self.map
+            .get(key)
+            .filter(|e| e.deadline > now)
+            .map(|e| e.deadline - now)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let idx = self.live_idx(key)?;
+        let (_, entry) = self.map.remove_idx(idx);
+        self.wheel.cancel(entry.timer);
+        Some(entry.val)
+    }
+
+    /// Reclaim every expired entry, returning the number of entries removed.
+    pub fn purge_expired(&mut self) -> usize {
+        let now = self.clock.now();
+        let map = &mut self.map;
+        let mut purged = 0;
+
+        // Every timer belongs to an entry, and is due exactly when that entry expires
+        self.wheel.advance(now, |key| {
+            map.remove(&key);
+            purged += 1;
+        });
+
+        purged
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        let now = self.clock.now();
+        self.map
+            .iter()
+            .filter(move |(_, e)| e.deadline > now)
+            .map(|(k, e)| (k, &e.val))
+    }
+
+    // Find the index of a live entry, reclaiming the entry if it has expired
+    fn live_idx(&mut self, key: &K) -> Option<usize> {
+        let idx = self.map.get_idx(key)?;
+        if unsafe { self.map.val_ref(idx) }.deadline > self.clock.now() {
+            Some(idx)
+        } else {
+            let (_, entry) = self.map.remove_idx(idx);
+            self.wheel.cancel(entry.timer);
+            None
+        }
+    }
+
+    // Reclaim the expired entries that a probe for `hash` passes over. With Robin Hood probing, those are the run of
+    // occupied slots that starts at the hash's intended slot.
+    fn reclaim_probe(&mut self, hash: u64, now: Duration) {
+        let cap = self.map.table.cap;
+        let mut idx = hash as usize & cap.wrapping_sub(1);
+        for _ in 0..cap {
+            if self.map.key(idx).is_none() {
+                break;
+            }
+
+            let entry = unsafe { self.map.val_ref(idx) };
+            if entry.deadline > now {
+                idx = (idx + 1) & (cap - 1);
+            } else {
+                // The rest of the run is shifted back, so the next entry to look at moves into this slot. The table is
+                // not shrunk, since that would move every entry.
+                let timer = entry.timer;
+                self.map.table.remove_at(idx);
+                self.wheel.cancel(timer);
+            }
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Default for ExpiringMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
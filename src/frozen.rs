@@ -0,0 +1,295 @@
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use fxhash::FxBuildHasher;
+
+use crate::{
+    HashMap,
+    layout::Layout,
+    probe::ProbeStrategy,
+};
+
+// The average number of keys per bucket. Larger buckets make for fewer pilots but a slower build.
+const BUCKET_SIZE: usize = 4;
+
+// Keys are placed into slightly more slots than there are keys, which makes finding pilots for the last buckets far
+// easier. The few keys that land beyond the end are then remapped into the holes left behind.
+const SLACK: usize = 100; // One extra slot per `SLACK` keys
+
+// The number of pilots tried for a bucket before giving up on a seed. Well spread hashes need a few thousand at most.
+const MAX_PILOTS: u32 = 1 << 16;
+
+// The number of pilots tried per key, across all buckets, before giving up on a seed. Well spread hashes need around
+// 20, while poorly spread ones may need millions in all, so this bounds the time spent on a seed that will not work.
+const PILOTS_PER_KEY: usize = 128;
+
+// The number of seeds tried before giving up on a perfect hash. Keys only collide under every seed if the hasher
+// ignores the seed, or treats them as the same. Together with the bounds on pilots, this makes building take time
+// linear in the number of keys.
+const MAX_SEEDS: u64 = 4;
+
+// Scatter a key's hash across the slots, as displaced by its bucket's pilot
+#[inline(always)]
+fn slot_for(hash: u64, pilot: u32, slots: usize) -> usize {
+    let mut x = hash ^ (pilot as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    x ^= x >> 33;
+    ((x as u128 * slots as u128) >> 64) as usize
+}
+
+// Skew keys so that 60% of them fall into the first 30% of buckets. These dense buckets are placed while the table is
+// still mostly empty, leaving the many sparse buckets to fill in the gaps.
+#[inline(always)]
+fn bucket_for(hash: u64, buckets: usize) -> usize {
+    let dense = buckets * 3 / 10;
+    if hash % 10 < 6 {
+        ((hash as u128 * dense as u128) >> 64) as usize
+    } else {
+        dense + ((hash as u128 * (buckets - dense) as u128) >> 64) as usize
+    }
+}
+
+// The parameters of a minimal perfect hash over a set of key hashes
+#[derive(Clone)]
+struct Mphf {
+    pilots: Vec<u32>,
+    slots: usize,
+    remap: Vec<u32>, // For each slot past the last key, the hole below it that it is moved to
+}
+
+impl Mphf {
+    // Find a pilot for every bucket such that each key lands in a distinct slot. Fails if no pilot is found for some
+    // bucket, or if the pilots tried run past the budget, which is only likely if the hashes are poorly spread.
+    fn build(hashes: &[u64]) -> Option<(Self, Vec<usize>)> {
+        let len = hashes.len();
+        let slots = len + len / SLACK + 1;
+        let bucket_count = len.div_ceil(BUCKET_SIZE);
+
+        let mut buckets = vec![Vec::new(); bucket_count];
+        for (i, hash) in hashes.iter().enumerate() {
+            buckets[bucket_for(*hash, bucket_count)].push(i);
+        }
+
+        // Place the largest buckets first, while there is still plenty of room
+        let mut order = (0..bucket_count).collect::<Vec<_>>();
+        order.sort_unstable_by_key(|b| std::cmp::Reverse(buckets[*b].len()));
+
+        let mut pilots = vec![0; bucket_count];
+        let mut positions = vec![0; len];
+        let mut taken = vec![false; slots];
+        let mut candidate = Vec::new();
+        let mut budget = len * PILOTS_PER_KEY;
+        for b in order {
+            let keys = &buckets[b];
+            if keys.is_empty() {
+                break;
+            }
+
+            let tries = budget.min(MAX_PILOTS as usize) as u32;
+            pilots[b] = (0..tries).find(|pilot| {
+                candidate.clear();
+                for i in keys {
+                    let slot = slot_for(hashes[*i], *pilot, slots);
+                    if taken[slot] || candidate.contains(&slot) {
+                        return false;
+                    }
+                    candidate.push(slot);
+                }
+                true
+            })?;
+            budget -= pilots[b] as usize + 1;
+
+            for (i, slot) in keys.iter().zip(candidate.iter()) {
+                positions[*i] = *slot;
+                taken[*slot] = true;
+            }
+        }
+
+        // Move keys beyond the end into the holes
+        let mut holes = (0..len).filter(|slot| !taken[*slot]);
+        let remap = (len..slots)
+            .map(|slot| if taken[slot] { holes.next().unwrap() as u32 } else { 0 })
+            .collect::<Vec<_>>();
+        for pos in &mut positions {
+            if *pos >= len {
+                *pos = remap[*pos - len] as usize;
+            }
+        }
+
+        Some((Self { pilots, slots, remap }, positions))
+    }
+
+    #[inline(always)]
+    fn idx_for(&self, hash: u64, len: usize) -> usize {
+        let pilot = self.pilots[bucket_for(hash, self.pilots.len())];
+        let slot = slot_for(hash, pilot, self.slots);
+        if slot < len {
+            slot
+        } else {
+            self.remap[slot - len] as usize
+        }
+    }
+}
+
+// How keys are found among the entries
+#[derive(Clone)]
+enum Index {
+    // The position of each key is given by a minimal perfect hash
+    Perfect(Mphf),
+    // Entries are sorted by the hash of their key, which is kept here, for a binary search. Used when keys' hashes
+    // collide under every seed, so that no perfect hash exists.
+    Sorted(Box<[u64]>),
+}
+
+/// A read-only map built by `HashMap::freeze`. Keys are placed using a minimal perfect hash, so every lookup takes
+/// exactly one probe and at most one key comparison, and entries are stored without any empty slots.
+///
+/// A perfect hash can only be built if every key has a distinct hash. If some keys' hashes still collide after they
+/// have been rehashed with several seeds then the hasher is at fault, and the map falls back to a binary search over
+/// the hashes of its keys. The same happens if the hashes are too poorly spread for a perfect hash to be found
+/// quickly, so building a map always takes time linear in the number of keys.
+#[derive(Clone)]
+pub struct FrozenMap<K: Hash + Eq, V, S: BuildHasher + Default = FxBuildHasher> {
+    entries: Box<[(K, V)]>,
+    index: Index,
+    seed: u64,
+
+    hasher: S,
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Default> FrozenMap<K, V, S> {
+    // Private interface
+
+    // Fast hashers leave the hashes of similar keys, such as consecutive integers, evenly spaced. The hash is mixed so
+    // that they are spread across the buckets instead, which keeps the pilots needed low whatever the hasher.
+    #[inline(always)]
+    fn hash_of(key: &K, seed: u64, hasher: &S) -> u64 {
+        let mut hasher = hasher.build_hasher();
+        hasher.write_u64(seed);
+        key.hash(&mut hasher);
+        let mut x = hasher.finish();
+        x ^= x >> 33;
+        x = x.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+        x ^= x >> 33;
+        x
+    }
+
+    fn from_entries(entries: Vec<(K, V)>, hasher: S) -> Self {
+        // Keys whose hashes collide cannot be told apart by any pilot, so rehash them with a new seed until they differ
+        for seed in 0..MAX_SEEDS {
+            let hashes = entries
+                .iter()
+                .map(|(k, _)| Self::hash_of(k, seed, &hasher))
+                .collect::<Vec<_>>();
+
+            let mut sorted = hashes.clone();
+            sorted.sort_unstable();
+            if sorted.windows(2).any(|w| w[0] == w[1]) {
+                continue;
+            }
+
+            if let Some((mphf, positions)) = Mphf::build(&hashes) {
+                let mut placed = positions.into_iter().zip(entries).collect::<Vec<_>>();
+                placed.sort_unstable_by_key(|(idx, _)| *idx);
+
+                return Self {
+                    entries: placed.into_iter().map(|(_, entry)| entry).collect(),
+                    index: Index::Perfect(mphf),
+                    seed,
+
+                    hasher,
+                };
+            }
+        }
+
+        let mut placed = entries
+            .into_iter()
+            .map(|entry| (Self::hash_of(&entry.0, 0, &hasher), entry))
+            .collect::<Vec<_>>();
+        placed.sort_unstable_by_key(|(hash, _)| *hash);
+        let (hashes, entries): (Vec<_>, Vec<_>) = placed.into_iter().unzip();
+
+        Self {
+            entries: entries.into_boxed_slice(),
+            index: Index::Sorted(hashes.into_boxed_slice()),
+            seed: 0,
+
+            hasher,
+        }
+    }
+
+    #[inline(always)]
+    fn get_idx(&self, key: &K) -> Option<usize> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        let hash = Self::hash_of(key, self.seed, &self.hasher);
+        match &self.index {
+            Index::Perfect(mphf) => {
+                let idx = mphf.idx_for(hash, self.entries.len());
+                if self.entries[idx].0.eq(key) {
+                    Some(idx)
+                } else {
+                    None
+                }
+            },
+            Index::Sorted(hashes) => {
+                let start = hashes.partition_point(|h| *h < hash);
+                (start..hashes.len())
+                    .take_while(|idx| hashes[*idx] == hash)
+                    .find(|idx| self.entries[*idx].0.eq(key))
+            },
+        }
+    }
+
+    // Public interface
+
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.get_idx(key).map(|idx| &self.entries[idx].1)
+    }
+
+    pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
+        self.get_idx(key).map(|idx| {
+            let (k, v) = &self.entries[idx];
+            (k, v)
+        })
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get_idx(key).is_some()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.entries.iter().map(|(_, v)| v)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Default, P: ProbeStrategy, L: Layout> HashMap<K, V, S, P, L> {
+    /// Turn the map into a read-only `FrozenMap` with faster lookups.
+    pub fn freeze(mut self) -> FrozenMap<K, V, S> {
+        let entries = self.take_entries();
+        let hasher = std::mem::take(&mut self.hasher);
+        FrozenMap::from_entries(entries, hasher)
+    }
+}
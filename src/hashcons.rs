@@ -0,0 +1,242 @@
+use std::{
+    hash::{BuildHasher, Hash, Hasher},
+    ops::Deref,
+    rc::{self, Rc},
+    sync::{self, Arc, Mutex},
+};
+
+use fxhash::FxBuildHasher;
+
+use crate::{
+    HashMap,
+    hasher::BuildIdentityHasher,
+};
+
+// A reference-counted pointer with weak references, so that tables can be written once for both `Rc` and `Arc`
+trait Shared<T>: Deref<Target = T> + Sized {
+    type Weak;
+
+    fn new(val: T) -> Self;
+    fn downgrade(this: &Self) -> Self::Weak;
+    fn upgrade(weak: &Self::Weak) -> Option<Self>;
+    fn is_dead(weak: &Self::Weak) -> bool;
+    fn weak_ptr_eq(a: &Self::Weak, b: &Self::Weak) -> bool;
+}
+
+impl<T> Shared<T> for Rc<T> {
+    type Weak = rc::Weak<T>;
+
+    fn new(val: T) -> Self { Rc::new(val) }
+    fn downgrade(this: &Self) -> Self::Weak { Rc::downgrade(this) }
+    fn upgrade(weak: &Self::Weak) -> Option<Self> { weak.upgrade() }
+    fn is_dead(weak: &Self::Weak) -> bool { weak.strong_count() == 0 }
+    fn weak_ptr_eq(a: &Self::Weak, b: &Self::Weak) -> bool { a.ptr_eq(b) }
+}
+
+impl<T> Shared<T> for Arc<T> {
+    type Weak = sync::Weak<T>;
+
+    fn new(val: T) -> Self { Arc::new(val) }
+    fn downgrade(this: &Self) -> Self::Weak { Arc::downgrade(this) }
+    fn upgrade(weak: &Self::Weak) -> Option<Self> { weak.upgrade() }
+    fn is_dead(weak: &Self::Weak) -> bool { weak.strong_count() == 0 }
+    fn weak_ptr_eq(a: &Self::Weak, b: &Self::Weak) -> bool { a.ptr_eq(b) }
+}
+
+// A weak reference to a consed value, along with the value's hash so that dead entries keep their place in the table
+struct Entry<T, P: Shared<T>> {
+    hash: u64,
+    weak: P::Weak,
+}
+
+impl<T, P: Shared<T>> Hash for Entry<T, P> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+impl<T, P: Shared<T>> PartialEq for Entry<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        P::weak_ptr_eq(&self.weak, &other.weak)
+    }
+}
+
+impl<T, P: Shared<T>> Eq for Entry<T, P> {}
+
+struct Table<T: Hash + Eq, P: Shared<T>, S: BuildHasher + Default> {
+    entries: HashMap<Entry<T, P>, (), BuildIdentityHasher>,
+    hasher: S,
+}
+
+impl<T: Hash + Eq, P: Shared<T>, S: BuildHasher + Default> Table<T, P, S> {
+    fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self {
+            entries: HashMap::with_capacity(capacity),
+            hasher,
+        }
+    }
+
+    #[inline(always)]
+    fn hash(&self, val: &T) -> u64 {
+        self.hasher.hash_one(val)
+    }
+
+    // Find the live pointer to a value equal to `val`, or a hint for inserting it
+    #[inline(always)]
+    fn find(&self, hash: u64, val: &T) -> Result<P, usize> {
+        let mut found = None;
+        self.entries
+            .probe_idx(hash, |entry| entry.hash == hash && match P::upgrade(&entry.weak) {
+                Some(ptr) if *ptr == *val => {
+                    found = Some(ptr);
+                    true
+                },
+                _ => false,
+            })
+            .map(|_| found.unwrap())
+    }
+
+    fn get(&self, val: &T) -> Option<P> {
+        self.find(self.hash(val), val).ok()
+    }
+
+    fn intern(&mut self, val: T) -> P {
+        // Make room by collecting dead entries before resorting to growing the table
+        if self.entries.len() == self.entries.capacity() {
+            self.collect_garbage();
+        }
+        self.entries.try_grow();
+
+        let hash = self.hash(&val);
+        match self.find(hash, &val) {
+            Ok(ptr) => ptr,
+            Err(hint) => {
+                let ptr = P::new(val);
+                self.entries.insert_at(hint, hash, Entry { hash, weak: P::downgrade(&ptr) }, ());
+                ptr
+            },
+        }
+    }
+
+    fn collect_garbage(&mut self) -> usize {
+        let len = self.entries.len();
+        self.entries.retain(|entry, _| !P::is_dead(&entry.weak));
+        len - self.entries.len()
+    }
+
+    fn live_count(&self) -> usize {
+        self.entries
+            .keys()
+            .filter(|entry| !P::is_dead(&entry.weak))
+            .count()
+    }
+}
+
+/// Deduplicates structurally equal values, handing out shared `Rc`s to a single copy of each. The table only holds
+/// weak references, so values are freed as soon as the last `Rc` to them is dropped.
+pub struct HashCons<T: Hash + Eq, S: BuildHasher + Default = FxBuildHasher> {
+    table: Table<T, Rc<T>, S>,
+}
+
+impl<T: Hash + Eq, S: BuildHasher + Default> HashCons<T, S> {
+    pub fn new() -> Self {
+        Self::with_capacity_and_hasher(0, Default::default())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, Default::default())
+    }
+
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self { table: Table::with_capacity_and_hasher(capacity, hasher) }
+    }
+
+    /// The number of entries in the table, including those whose values have died but not yet been collected.
+    pub fn len(&self) -> usize {
+        self.table.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of values that are still alive.
+    pub fn live_count(&self) -> usize {
+        self.table.live_count()
+    }
+
+    /// Get the shared copy of `val`, creating it if no equal value is alive.
+    pub fn intern(&mut self, val: T) -> Rc<T> {
+        self.table.intern(val)
+    }
+
+    /// Get the shared copy of `val` if an equal value is alive.
+    pub fn get(&self, val: &T) -> Option<Rc<T>> {
+        self.table.get(val)
+    }
+
+    /// Remove the entries of values that have been dropped, returning the number removed.
+    pub fn collect_garbage(&mut self) -> usize {
+        self.table.collect_garbage()
+    }
+}
+
+impl<T: Hash + Eq, S: BuildHasher + Default> Default for HashCons<T, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A `HashCons` that hands out `Arc`s and may be shared between threads.
+pub struct SyncHashCons<T: Hash + Eq, S: BuildHasher + Default = FxBuildHasher> {
+    table: Mutex<Table<T, Arc<T>, S>>,
+}
+
+impl<T: Hash + Eq, S: BuildHasher + Default> SyncHashCons<T, S> {
+    pub fn new() -> Self {
+        Self::with_capacity_and_hasher(0, Default::default())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, Default::default())
+    }
+
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self { table: Mutex::new(Table::with_capacity_and_hasher(capacity, hasher)) }
+    }
+
+    /// The number of entries in the table, including those whose values have died but not yet been collected.
+    pub fn len(&self) -> usize {
+        self.table.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of values that are still alive.
+    pub fn live_count(&self) -> usize {
+        self.table.lock().unwrap().live_count()
+    }
+
+    /// Get the shared copy of `val`, creating it if no equal value is alive.
+    pub fn intern(&self, val: T) -> Arc<T> {
+        self.table.lock().unwrap().intern(val)
+    }
+
+    /// Get the shared copy of `val` if an equal value is alive.
+    pub fn get(&self, val: &T) -> Option<Arc<T>> {
+        self.table.lock().unwrap().get(val)
+    }
+
+    /// Remove the entries of values that have been dropped, returning the number removed.
+    pub fn collect_garbage(&self) -> usize {
+        self.table.lock().unwrap().collect_garbage()
+    }
+}
+
+impl<T: Hash + Eq, S: BuildHasher + Default> Default for SyncHashCons<T, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,43 @@
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+
+// A hasher that passes integers through untouched, for keys that are already hashes (or otherwise well-distributed)
+#[derive(Default)]
+pub(crate) struct IdentityHasher {
+    hash: u64,
+}
+
+impl Hasher for IdentityHasher {
+    #[inline(always)]
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash = self.hash.rotate_left(8) ^ byte as u64;
+        }
+    }
+
+    #[inline(always)]
+    fn write_u64(&mut self, i: u64) {
+        self.hash = i;
+    }
+
+    #[inline(always)]
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+pub(crate) type BuildIdentityHasher = BuildHasherDefault<IdentityHasher>;
+
+// A value tagged with a precomputed hash, which is all that it hashes as. Tables keyed by `Hashed` use
+// `BuildIdentityHasher` so that the original value never needs to be hashed again.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub(crate) struct Hashed<T> {
+    pub hash: u64,
+    pub val: T,
+}
+
+impl<T> Hash for Hashed<T> {
+    #[inline(always)]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
@@ -0,0 +1,393 @@
+use std::{
+    any::Any,
+    fmt,
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use fxhash::FxBuildHasher;
+
+use crate::HashTable;
+
+// Collections are numbered so that an index handle from one is never used to query another
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Identifies a record within an `IndexedCollection`. Ids are reused once their record has been removed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RecordId(usize);
+
+impl RecordId {
+    pub fn as_usize(self) -> usize {
+        self.0
+    }
+}
+
+/// The error returned when a record would share a key with another record in a unique index. It hands back the
+/// rejected record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UniqueViolation<T> {
+    pub record: T,
+    pub existing: RecordId,
+}
+
+impl<T> fmt::Display for UniqueViolation<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "record violates a unique index held by record {}", self.existing.0)
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for UniqueViolation<T> {}
+
+/// A unique index of an `IndexedCollection`, as returned by `add_unique_index`. It may only be used with the
+/// collection that returned it.
+pub struct UniqueIndex<K> {
+    collection: u64,
+    idx: usize,
+    _phantom: PhantomData<fn(&K)>,
+}
+
+/// A non-unique index of an `IndexedCollection`, as returned by `add_index`. It may only be used with the collection
+/// that returned it.
+pub struct MultiIndex<K> {
+    collection: u64,
+    idx: usize,
+    _phantom: PhantomData<fn(&K)>,
+}
+
+impl<K> Copy for UniqueIndex<K> {}
+impl<K> Clone for UniqueIndex<K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for MultiIndex<K> {}
+impl<K> Clone for MultiIndex<K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+#[inline(always)]
+fn hash_of<K: Hash, S: BuildHasher>(key: &K, hasher: &S) -> u64 {
+    hasher.hash_one(key)
+}
+
+// The record with the given id, which must exist
+#[inline(always)]
+fn record<T>(records: &[Option<T>], id: RecordId) -> &T {
+    records[id.0].as_ref().unwrap()
+}
+
+// An index with its key type erased. The records referred to by the index must be present in `records`.
+trait Index<T, S> {
+    // The record that already holds the key of `record` in this index, other than `ignore`
+    fn conflict(&self, records: &[Option<T>], record: &T, ignore: Option<RecordId>, hasher: &S) -> Option<RecordId>;
+
+    // The record must already be present in `records`
+    fn insert(&mut self, records: &[Option<T>], id: RecordId, hasher: &S);
+
+    // The record must still be present in `records`
+    fn remove(&mut self, records: &[Option<T>], id: RecordId, hasher: &S);
+
+    fn as_any(&self) -> &dyn Any;
+}
+
+// Records are stored in the table by id and their keys are derived on demand, so keys are never duplicated
+struct Unique<T, K> {
+    key: Box<dyn Fn(&T) -> K>,
+    table: HashTable<RecordId>,
+}
+
+impl<T, K: Hash + Eq> Unique<T, K> {
+    fn find<S: BuildHasher>(&self, records: &[Option<T>], key: &K, hasher: &S) -> Option<RecordId> {
+        let key_of = &*self.key;
+        self.table.find(hash_of(key, hasher), |id| key_of(record(records, *id)) == *key).copied()
+    }
+}
+
+impl<T: 'static, K: Hash + Eq + 'static, S: BuildHasher> Index<T, S> for Unique<T, K> {
+    fn conflict(&self, records: &[Option<T>], record: &T, ignore: Option<RecordId>, hasher: &S) -> Option<RecordId> {
+        self.find(records, &(self.key)(record), hasher).filter(|id| Some(*id) != ignore)
+    }
+
+    fn insert(&mut self, records: &[Option<T>], id: RecordId, hasher: &S) {
+        let hash = hash_of(&(self.key)(record(records, id)), hasher);
+        self.table.insert_unique(hash, id);
+    }
+
+    fn remove(&mut self, records: &[Option<T>], id: RecordId, hasher: &S) {
+        let hash = hash_of(&(self.key)(record(records, id)), hasher);
+        self.table.remove(hash, |other| *other == id);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// Each entry is the group of records sharing a key, and is removed as soon as it would become empty, so every group
+// has a first record to compare keys with
+struct Multi<T, K> {
+    key: Box<dyn Fn(&T) -> K>,
+    table: HashTable<Vec<RecordId>>,
+}
+
+impl<T, K: Hash + Eq> Multi<T, K> {
+    fn find<S: BuildHasher>(&self, records: &[Option<T>], key: &K, hasher: &S) -> &[RecordId] {
+        let key_of = &*self.key;
+        self.table
+            .find(hash_of(key, hasher), |group| key_of(record(records, group[0])) == *key)
+            .map_or(&[], |group| group.as_slice())
+    }
+}
+
+impl<T: 'static, K: Hash + Eq + 'static, S: BuildHasher> Index<T, S> for Multi<T, K> {
+    fn conflict(&self, _: &[Option<T>], _: &T, _: Option<RecordId>, _: &S) -> Option<RecordId> {
+        None
+    }
+
+    fn insert(&mut self, records: &[Option<T>], id: RecordId, hasher: &S) {
+        let key_of = &*self.key;
+        let key = key_of(record(records, id));
+        self.table
+            .entry(hash_of(&key, hasher), |group| key_of(record(records, group[0])) == key)
+            .and_modify(|group| group.push(id))
+            .or_insert_with(|| vec![id]);
+    }
+
+    fn remove(&mut self, records: &[Option<T>], id: RecordId, hasher: &S) {
+        let key_of = &*self.key;
+        let key = key_of(record(records, id));
+        let hash = hash_of(&key, hasher);
+        let eq = |group: &Vec<RecordId>| key_of(record(records, group[0])) == key;
+
+        let group = self.table.find_mut(hash, eq).unwrap();
+        if group.len() > 1 {
+            group.retain(|other| *other != id);
+        } else {
+            self.table.remove(hash, eq);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// A collection that stores each record once and keeps any number of hash indexes over it consistent. Indexes are
+/// declared with a function that derives a key from a record, and may be unique or non-unique.
+///
+/// Every change is checked against all unique indexes before anything is modified, so an operation that would violate
+/// one fails without any effect.
+pub struct IndexedCollection<T: 'static, S: BuildHasher + Default = FxBuildHasher> {
+    id: u64,
+    records: Vec<Option<T>>,
+    free: Vec<usize>, // Slots that have been vacated and may be reused
+    len: usize,
+    indexes: Vec<Box<dyn Index<T, S>>>,
+
+    hasher: S,
+}
+
+impl<T: 'static, S: BuildHasher + Default> IndexedCollection<T, S> {
+    // Private interface
+
+    fn unique<K: Hash + Eq + 'static>(&self, index: UniqueIndex<K>) -> &Unique<T, K> {
+        assert_eq!(index.collection, self.id, "Index does not belong to this collection");
+        self.indexes[index.idx]
+            .as_any()
+            .downcast_ref()
+            .expect("Index does not belong to this collection")
+    }
+
+    fn multi<K: Hash + Eq + 'static>(&self, index: MultiIndex<K>) -> &Multi<T, K> {
+        assert_eq!(index.collection, self.id, "Index does not belong to this collection");
+        self.indexes[index.idx]
+            .as_any()
+            .downcast_ref()
+            .expect("Index does not belong to this collection")
+    }
+
+    fn check(&self, record: &T, ignore: Option<RecordId>) -> Result<(), RecordId> {
+        for index in &self.indexes {
+            if let Some(existing) = index.conflict(&self.records, record, ignore, &self.hasher) {
+                return Err(existing);
+            }
+        }
+        Ok(())
+    }
+
+    // Public interface
+
+    pub fn new() -> Self {
+        Self::with_hasher(Default::default())
+    }
+
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            records: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+            indexes: Vec::new(),
+
+            hasher,
+        }
+    }
+
+    /// Declare a unique index over the keys derived by `key`. Fails with the id of a record whose key is shared with
+    /// another if the existing records already violate the index.
+    pub fn add_unique_index<K, F>(&mut self, key: F) -> Result<UniqueIndex<K>, RecordId>
+    where
+        K: Hash + Eq + 'static,
+        F: Fn(&T) -> K + 'static,
+    {
+        let mut index = Unique { key: Box::new(key), table: HashTable::with_capacity(self.len) };
+        for (idx, record) in self.records.iter().enumerate() {
+            if let Some(record) = record {
+                if index.conflict(&self.records, record, None, &self.hasher).is_some() {
+                    return Err(RecordId(idx));
+                }
+                index.insert(&self.records, RecordId(idx), &self.hasher);
+            }
+        }
+
+        self.indexes.push(Box::new(index));
+        Ok(UniqueIndex { collection: self.id, idx: self.indexes.len() - 1, _phantom: PhantomData })
+    }
+
+    /// Declare a non-unique index over the keys derived by `key`.
+    pub fn add_index<K, F>(&mut self, key: F) -> MultiIndex<K>
+    where
+        K: Hash + Eq + 'static,
+        F: Fn(&T) -> K + 'static,
+    {
+        let mut index = Multi { key: Box::new(key), table: HashTable::new() };
+        for (idx, record) in self.records.iter().enumerate() {
+            if record.is_some() {
+                Index::<T, S>::insert(&mut index, &self.records, RecordId(idx), &self.hasher);
+            }
+        }
+
+        self.indexes.push(Box::new(index));
+        MultiIndex { collection: self.id, idx: self.indexes.len() - 1, _phantom: PhantomData }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn insert(&mut self, record: T) -> Result<RecordId, UniqueViolation<T>> {
+        if let Err(existing) = self.check(&record, None) {
+            return Err(UniqueViolation { record, existing });
+        }
+
+        let id = match self.free.pop() {
+            Some(idx) => {
+                self.records[idx] = Some(record);
+                RecordId(idx)
+            },
+            None => {
+                self.records.push(Some(record));
+                RecordId(self.records.len() - 1)
+            },
+        };
+        for index in &mut self.indexes {
+            index.insert(&self.records, id, &self.hasher);
+        }
+        self.len += 1;
+
+        Ok(id)
+    }
+
+    pub fn get(&self, id: RecordId) -> Option<&T> {
+        self.records.get(id.0).and_then(|record| record.as_ref())
+    }
+
+    pub fn contains(&self, id: RecordId) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Replace a record, returning the old one. Panics if there is no record with the given id.
+    pub fn replace(&mut self, id: RecordId, record: T) -> Result<T, UniqueViolation<T>> {
+        assert!(self.contains(id), "Record does not exist");
+        if let Err(existing) = self.check(&record, Some(id)) {
+            return Err(UniqueViolation { record, existing });
+        }
+
+        for index in &mut self.indexes {
+            index.remove(&self.records, id, &self.hasher);
+        }
+        let old = self.records[id.0].replace(record);
+        for index in &mut self.indexes {
+            index.insert(&self.records, id, &self.hasher);
+        }
+
+        Ok(old.unwrap())
+    }
+
+    /// Modify a record in place. The modification is made to a copy, so that the record is left untouched if the result
+    /// would violate a unique index. Panics if there is no record with the given id.
+    pub fn update<F: FnOnce(&mut T)>(&mut self, id: RecordId, f: F) -> Result<(), UniqueViolation<T>> where T: Clone {
+        let mut record = self.get(id).expect("Record does not exist").clone();
+        f(&mut record);
+        self.replace(id, record).map(drop)
+    }
+
+    pub fn remove(&mut self, id: RecordId) -> Option<T> {
+        if !self.contains(id) {
+            return None;
+        }
+
+        for index in &mut self.indexes {
+            index.remove(&self.records, id, &self.hasher);
+        }
+        self.free.push(id.0);
+        self.len -= 1;
+        self.records[id.0].take()
+    }
+
+    /// Find the record with the given key in a unique index. Panics if the index belongs to another collection.
+    pub fn get_by<K: Hash + Eq + 'static>(&self, index: UniqueIndex<K>, key: &K) -> Option<(RecordId, &T)> {
+        self.unique(index)
+            .find(&self.records, key, &self.hasher)
+            .map(|id| (id, record(&self.records, id)))
+    }
+
+    /// Every record with the given key in a non-unique index, in the order that they were indexed. Panics if the index
+    /// belongs to another collection.
+    pub fn get_all_by<K: Hash + Eq + 'static>(
+        &self,
+        index: MultiIndex<K>,
+        key: &K,
+    ) -> impl Iterator<Item = (RecordId, &T)> + '_ {
+        self.multi(index)
+            .find(&self.records, key, &self.hasher)
+            .iter()
+            .map(move |id| (*id, record(&self.records, *id)))
+    }
+
+    pub fn remove_by<K: Hash + Eq + 'static>(&mut self, index: UniqueIndex<K>, key: &K) -> Option<T> {
+        let (id, _) = self.get_by(index, key)?;
+        self.remove(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (RecordId, &T)> + '_ {
+        self.records
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, record)| record.as_ref().map(|record| (RecordId(idx), record)))
+    }
+}
+
+impl<T: 'static, S: BuildHasher + Default> Default for IndexedCollection<T, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
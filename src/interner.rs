@@ -0,0 +1,220 @@
+use std::{
+    hash::BuildHasher,
+    sync::RwLock,
+};
+
+use fxhash::FxBuildHasher;
+
+use crate::{
+    HashMap,
+    hasher::{BuildIdentityHasher, Hashed},
+};
+
+// The minimum size of each arena chunk, in bytes
+const CHUNK_SIZE: usize = 4096;
+
+/// A compact handle to a string held by an `Interner`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+
+    #[inline(always)]
+    fn idx(self) -> usize {
+        self.0 as usize
+    }
+}
+
+// Append-only string storage. Chunks are allocated with a fixed capacity and never grow, so the strings within them
+// never move for as long as the arena lives.
+struct Arena {
+    chunks: Vec<String>,
+}
+
+impl Arena {
+    fn new() -> Self {
+        Self { chunks: Vec::new() }
+    }
+
+    // The returned reference is only valid for as long as the arena is alive
+    unsafe fn alloc(&mut self, s: &str) -> &'static str {
+        let fits = self.chunks
+            .last()
+            .is_some_and(|chunk| chunk.capacity() - chunk.len() >= s.len());
+        if !fits {
+            self.chunks.push(String::with_capacity(s.len().max(CHUNK_SIZE)));
+        }
+
+        let chunk = self.chunks.last_mut().unwrap();
+        let start = chunk.len();
+        chunk.push_str(s);
+        &*(&chunk[start..] as *const str)
+    }
+}
+
+/// Maps strings to `Symbol`s, storing each distinct string once.
+pub struct Interner<S: BuildHasher + Default = FxBuildHasher> {
+    table: HashMap<Hashed<Symbol>, (), BuildIdentityHasher>,
+    strs: Vec<&'static str>, // Borrowed from `arena`
+    arena: Arena,
+
+    hasher: S,
+}
+
+impl<S: BuildHasher + Default> Interner<S> {
+    // Private interface
+
+    #[inline(always)]
+    fn hash(&self, s: &str) -> u64 {
+        self.hasher.hash_one(s)
+    }
+
+    #[inline(always)]
+    fn find(&self, hash: u64, s: &str) -> Result<usize, usize> {
+        let strs = &self.strs;
+        self.table.probe_idx(hash, |sym| sym.hash == hash && strs[sym.val.idx()] == s)
+    }
+
+    // Public interface
+
+    pub fn new() -> Self {
+        Self::with_capacity_and_hasher(0, Default::default())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, Default::default())
+    }
+
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self {
+            table: HashMap::with_capacity(capacity),
+            strs: Vec::with_capacity(capacity),
+            arena: Arena::new(),
+
+            hasher,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.strs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strs.is_empty()
+    }
+
+    /// Get the symbol for `s`, interning it if it has not been seen before.
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        let hash = self.hash(s);
+
+        self.table.try_grow();
+        match self.find(hash, s) {
+            Ok(idx) => self.table.key(idx).unwrap().val,
+            Err(hint) => {
+                assert!(self.strs.len() < u32::MAX as usize, "Interner is full");
+                let sym = Symbol(self.strs.len() as u32);
+                self.strs.push(unsafe { self.arena.alloc(s) });
+                self.table.insert_at(hint, hash, Hashed { hash, val: sym }, ());
+                sym
+            },
+        }
+    }
+
+    /// Get the symbol for `s` without interning it.
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.find(self.hash(s), s)
+            .ok()
+            .map(|idx| self.table.key(idx).unwrap().val)
+    }
+
+    /// The string that `sym` refers to. Panics if `sym` was not produced by this interner.
+    pub fn resolve(&self, sym: Symbol) -> &str {
+        self.strs[sym.idx()]
+    }
+
+    pub fn try_resolve(&self, sym: Symbol) -> Option<&str> {
+        self.strs.get(sym.idx()).copied()
+    }
+
+    /// Every interned string and its symbol, in the order that they were first interned.
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &str)> + '_ {
+        self.strs
+            .iter()
+            .enumerate()
+            .map(|(idx, s)| (Symbol(idx as u32), *s))
+    }
+}
+
+impl<S: BuildHasher + Default> Default for Interner<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An `Interner` that may be shared between threads.
+pub struct SyncInterner<S: BuildHasher + Default = FxBuildHasher> {
+    inner: RwLock<Interner<S>>,
+}
+
+impl<S: BuildHasher + Default> SyncInterner<S> {
+    pub fn new() -> Self {
+        Self::with_capacity_and_hasher(0, Default::default())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, Default::default())
+    }
+
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self { inner: RwLock::new(Interner::with_capacity_and_hasher(capacity, hasher)) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn intern(&self, s: &str) -> Symbol {
+        // Most strings have already been interned, so try under the shared lock first
+        if let Some(sym) = self.get(s) {
+            return sym;
+        }
+        self.inner.write().unwrap().intern(s)
+    }
+
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.inner.read().unwrap().get(s)
+    }
+
+    pub fn resolve(&self, sym: Symbol) -> &str {
+        self.try_resolve(sym).expect("Symbol does not belong to this interner")
+    }
+
+    pub fn try_resolve(&self, sym: Symbol) -> Option<&str> {
+        // Strings never move within the arena, so they outlive the lock guard
+        let s: Option<&'static str> = self.inner.read().unwrap().strs.get(sym.idx()).copied();
+        s
+    }
+
+    pub fn into_inner(self) -> Interner<S> {
+        self.inner.into_inner().unwrap()
+    }
+}
+
+impl<S: BuildHasher + Default> Default for SyncInterner<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: BuildHasher + Default> From<Interner<S>> for SyncInterner<S> {
+    fn from(interner: Interner<S>) -> Self {
+        Self { inner: RwLock::new(interner) }
+    }
+}
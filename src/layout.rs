@@ -0,0 +1,703 @@
+//! Storage layouts for `HashMap`.
+//!
+//! A layout decides how a table's keys and values are arranged in memory. It is chosen with the `L` type parameter of
+//! `HashMap`, and every map operation behaves identically whichever layout is used.
+
+use std::{
+    alloc,
+    mem::{self, MaybeUninit},
+    ptr::{self, NonNull},
+};
+
+use crate::probe::Slots;
+
+/// The slots of a `HashMap`, as laid out in memory by some `Layout`.
+pub trait Storage: Slots {
+    /// No slots at all. Unlike `with_capacity(0)`, this can be used in a const context.
+    const EMPTY: Self;
+
+    /// Allocate `cap` empty slots.
+    fn with_capacity(cap: usize) -> Self;
+
+    /// Copy every entry into new slots at the same positions, so the copy is probed exactly like the original.
+    fn clone_slots(&self) -> Self where Self: Sized, Self::Key: Clone, Self::Val: Clone {
+        let mut slots = Self::with_capacity(self.cap());
+        slots.clone_slots_from(self);
+        slots
+    }
+
+    /// Make these slots a copy of `source` in the same way as `clone_slots`, reusing their allocation if they have the
+    /// same capacity. When keys and values are primitives, or tuples and arrays of them, whole buffers are copied at
+    /// once.
+    fn clone_slots_from(&mut self, source: &Self) where Self::Key: Clone, Self::Val: Clone;
+
+    /// The first occupied slot at or after `from`.
+    fn next_occupied(&self, from: usize) -> Option<usize>;
+
+    /// Hint that the slot at `idx` is about to be probed, so that its memory can be fetched ahead of time.
+    fn prefetch(&self, idx: usize);
+
+    /// The key in an occupied slot.
+    ///
+    /// # Safety
+    ///
+    /// The slot at `idx` must be occupied.
+    unsafe fn key_ref(&self, idx: usize) -> &Self::Key;
+
+    /// The value in an occupied slot.
+    ///
+    /// # Safety
+    ///
+    /// The slot at `idx` must be occupied.
+    unsafe fn val_ref(&self, idx: usize) -> &Self::Val;
+
+    /// A pointer to the key in a slot. It may only be dereferenced while the slot is occupied, and only written through
+    /// while nothing else borrows the key, so callers that hand out a `&mut` from it must hold the slots mutably.
+    ///
+    /// # Safety
+    ///
+    /// `idx` must be less than the capacity.
+    unsafe fn key_ptr(&self, idx: usize) -> *mut Self::Key;
+
+    /// A pointer to the value in a slot, with the same rules as `key_ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `idx` must be less than the capacity.
+    unsafe fn val_ptr(&self, idx: usize) -> *mut Self::Val;
+}
+
+/// An arrangement of a table's keys and values in memory.
+pub trait Layout {
+    type Storage<K, V>: Storage<Key = K, Val = V>;
+}
+
+// Hint that the memory at `ptr` will soon be read. This never faults, even for memory that is not allocated.
+#[inline(always)]
+fn prefetch<T>(ptr: *const T) {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(ptr as *const i8)
+    };
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = ptr;
+}
+
+// Types whose `Clone` is known to copy their bytes. Specializing on `Copy` itself would be unsound, since whether a
+// type is `Copy` can depend on its lifetimes, so this is only implemented for primitives and tuples and arrays of them
+// (and for the keys of a `RawTable`, which are stored along with their hash).
+#[rustc_specialization_trait]
+pub(crate) trait TrivialClone: Copy {}
+
+macro_rules! trivial_clone {
+    ($($t:ty),*) => { $(impl TrivialClone for $t {})* };
+}
+
+trivial_clone!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char, ());
+
+impl<A: TrivialClone, B: TrivialClone> TrivialClone for (A, B) {}
+
+impl<T: TrivialClone, const N: usize> TrivialClone for [T; N] {}
+
+// Whether cloning a `T` is the same as copying its bytes
+trait IsCopy {
+    fn is_copy() -> bool;
+}
+
+impl<T> IsCopy for T {
+    default fn is_copy() -> bool {
+        false
+    }
+}
+
+impl<T: TrivialClone> IsCopy for T {
+    fn is_copy() -> bool {
+        true
+    }
+}
+
+// Give `slots` the same capacity as `source` and drop their entries, ready to be made a copy of it. Copying bytes
+// overwrites every slot, so entries that need no dropping are left in place.
+fn prepare_clone<T: Storage>(slots: &mut T, source: &T) {
+    if slots.cap() != source.cap() {
+        *slots = T::with_capacity(source.cap());
+    } else if !<(T::Key, T::Val)>::is_copy() {
+        drop_entries(slots);
+    }
+}
+
+// Drop every entry, leaving the slots empty
+fn drop_entries<T: Storage>(slots: &mut T) {
+    let mut idx = 0;
+    while let Some(occupied) = slots.next_occupied(idx) {
+        drop(unsafe { slots.take(occupied) });
+        idx = occupied + 1;
+    }
+}
+
+// Clone every entry of `source` into the same slot of `slots`, which must be empty and have the same capacity
+fn clone_entries<T: Storage>(slots: &mut T, source: &T) where T::Key: Clone, T::Val: Clone {
+    let mut idx = 0;
+    while let Some(occupied) = source.next_occupied(idx) {
+        unsafe { slots.put(occupied, source.key_ref(occupied).clone(), source.val_ref(occupied).clone()) };
+        idx = occupied + 1;
+    }
+}
+
+// A fixed-size allocation of `T`s, initialised only where its owner has written to it. Unlike `RawVec`, an empty one
+// can be made in a const context.
+struct Buf<T> {
+    ptr: NonNull<T>,
+    cap: usize,
+}
+
+impl<T> Buf<T> {
+    const EMPTY: Self = Self { ptr: NonNull::dangling(), cap: 0 };
+
+    fn allocate(cap: usize, zeroed: bool) -> Self {
+        let layout = alloc::Layout::array::<T>(cap).expect("capacity overflow");
+        if layout.size() == 0 {
+            return Self { ptr: NonNull::dangling(), cap };
+        }
+
+        let ptr = unsafe { if zeroed { alloc::alloc_zeroed(layout) } else { alloc::alloc(layout) } };
+        match NonNull::new(ptr as *mut T) {
+            Some(ptr) => Self { ptr, cap },
+            None => alloc::handle_alloc_error(layout),
+        }
+    }
+
+    fn with_capacity(cap: usize) -> Self {
+        Self::allocate(cap, false)
+    }
+
+    fn with_capacity_zeroed(cap: usize) -> Self {
+        Self::allocate(cap, true)
+    }
+
+    #[inline(always)]
+    fn ptr(&self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+
+    // Overwrite the contents with those of a buffer of the same capacity, initialised or not
+    fn copy_from(&mut self, source: &Self) {
+        assert_eq!(self.cap, source.cap);
+        unsafe { ptr::copy_nonoverlapping(source.ptr(), self.ptr(), self.cap) };
+    }
+
+    // The following require that `idx < cap`
+
+    #[inline(always)]
+    unsafe fn get(&self, idx: usize) -> T {
+        ptr::read(self.ptr().add(idx))
+    }
+
+    #[inline(always)]
+    unsafe fn set(&mut self, idx: usize, val: T) {
+        ptr::write(self.ptr().add(idx), val)
+    }
+
+    #[inline(always)]
+    unsafe fn get_ref(&self, idx: usize) -> &T {
+        &*self.ptr().add(idx)
+    }
+
+    #[inline(always)]
+    unsafe fn get_mut(&mut self, idx: usize) -> &mut T {
+        &mut *self.ptr().add(idx)
+    }
+
+}
+
+impl<T> Drop for Buf<T> {
+    fn drop(&mut self) {
+        let layout = alloc::Layout::array::<T>(self.cap).unwrap();
+        if layout.size() != 0 {
+            unsafe { alloc::dealloc(self.ptr() as *mut u8, layout) };
+        }
+    }
+}
+
+// The buffer uniquely owns its contents, just like a `Vec` does
+unsafe impl<T: Send> Send for Buf<T> {}
+unsafe impl<T: Sync> Sync for Buf<T> {}
+
+const WORD_BITS: usize = 64;
+
+// One bit per slot, set where the slot is occupied. It is allocated zeroed, so no slot needs initialising, and empty
+// regions of a table can be skipped a word at a time.
+struct Occupancy {
+    words: Buf<u64>,
+}
+
+impl Occupancy {
+    const EMPTY: Self = Self { words: Buf::EMPTY };
+
+    fn with_capacity(cap: usize) -> Self {
+        Self { words: Buf::with_capacity_zeroed(cap.div_ceil(WORD_BITS)) }
+    }
+
+    #[inline(always)]
+    fn get(&self, idx: usize) -> bool {
+        let word = unsafe { self.words.get(idx / WORD_BITS) };
+        word & (1 << (idx % WORD_BITS)) != 0
+    }
+
+    #[inline(always)]
+    fn set(&mut self, idx: usize) {
+        unsafe { *self.words.get_mut(idx / WORD_BITS) |= 1 << (idx % WORD_BITS) };
+    }
+
+    #[inline(always)]
+    fn unset(&mut self, idx: usize) {
+        unsafe { *self.words.get_mut(idx / WORD_BITS) &= !(1 << (idx % WORD_BITS)) };
+    }
+
+    #[inline(always)]
+    fn prefetch(&self, idx: usize) {
+        prefetch((self.words.ptr() as *const u64).wrapping_add(idx / WORD_BITS));
+    }
+
+    #[inline(always)]
+    fn next(&self, from: usize, cap: usize) -> Option<usize> {
+        if from >= cap {
+            return None;
+        }
+
+        let mut word_idx = from / WORD_BITS;
+        let mut word = unsafe { self.words.get(word_idx) } & (!0 << (from % WORD_BITS));
+        loop {
+            if word != 0 {
+                // Bits beyond the capacity are never set
+                return Some(word_idx * WORD_BITS + word.trailing_zeros() as usize);
+            }
+
+            word_idx += 1;
+            if word_idx * WORD_BITS >= cap {
+                return None;
+            }
+            word = unsafe { self.words.get(word_idx) };
+        }
+    }
+}
+
+/// Keys and values in two separate arrays. Probing only touches keys, so as many as possible share a cache line, but a
+/// successful lookup touches a second cache line for its value. This is the default.
+pub struct SoA;
+
+impl Layout for SoA {
+    type Storage<K, V> = SoASlots<K, V>;
+}
+
+pub struct SoASlots<K, V> {
+    occupied: Occupancy,
+    keys: Buf<K>, // Only initialised where the slot is occupied
+    vals: Buf<V>,
+    cap: usize,
+}
+
+impl<K, V> Slots for SoASlots<K, V> {
+    type Key = K;
+    type Val = V;
+
+    #[inline(always)]
+    fn cap(&self) -> usize {
+        self.cap
+    }
+
+    #[inline(always)]
+    fn key(&self, idx: usize) -> Option<&K> {
+        if self.occupied.get(idx) {
+            Some(unsafe { self.keys.get_ref(idx) })
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn take(&mut self, idx: usize) -> (K, V) {
+        self.occupied.unset(idx);
+        (self.keys.get(idx), self.vals.get(idx))
+    }
+
+    #[inline(always)]
+    unsafe fn put(&mut self, idx: usize, key: K, val: V) {
+        self.occupied.set(idx);
+        self.keys.set(idx, key);
+        self.vals.set(idx, val);
+    }
+
+    #[inline(always)]
+    unsafe fn swap(&mut self, idx: usize, key: &mut K, val: &mut V) {
+        mem::swap(key, self.keys.get_mut(idx));
+        mem::swap(val, self.vals.get_mut(idx));
+    }
+
+    #[inline(always)]
+    unsafe fn shift(&mut self, from: usize, to: usize) {
+        self.occupied.unset(from);
+        self.occupied.set(to);
+        self.keys.set(to, self.keys.get(from));
+        self.vals.set(to, self.vals.get(from));
+    }
+}
+
+impl<K, V> Storage for SoASlots<K, V> {
+    const EMPTY: Self = Self {
+        occupied: Occupancy::EMPTY,
+        keys: Buf::EMPTY,
+        vals: Buf::EMPTY,
+        cap: 0,
+    };
+
+    fn with_capacity(cap: usize) -> Self {
+        Self {
+            occupied: Occupancy::with_capacity(cap),
+            keys: Buf::with_capacity(cap),
+            vals: Buf::with_capacity(cap),
+            cap,
+        }
+    }
+
+    fn clone_slots_from(&mut self, source: &Self) where K: Clone, V: Clone {
+        prepare_clone(self, source);
+        if <(K, V)>::is_copy() {
+            // Unoccupied slots are copied too, which is harmless and avoids checking each one
+            self.occupied.words.copy_from(&source.occupied.words);
+            self.keys.copy_from(&source.keys);
+            self.vals.copy_from(&source.vals);
+        } else {
+            clone_entries(self, source);
+        }
+    }
+
+    #[inline(always)]
+    fn next_occupied(&self, from: usize) -> Option<usize> {
+        self.occupied.next(from, self.cap)
+    }
+
+    #[inline(always)]
+    fn prefetch(&self, idx: usize) {
+        self.occupied.prefetch(idx);
+        prefetch((self.keys.ptr() as *const K).wrapping_add(idx));
+    }
+
+    #[inline(always)]
+    unsafe fn key_ref(&self, idx: usize) -> &K {
+        self.keys.get_ref(idx)
+    }
+
+    #[inline(always)]
+    unsafe fn val_ref(&self, idx: usize) -> &V {
+        self.vals.get_ref(idx)
+    }
+
+    #[inline(always)]
+    unsafe fn key_ptr(&self, idx: usize) -> *mut K {
+        self.keys.ptr().add(idx)
+    }
+
+    #[inline(always)]
+    unsafe fn val_ptr(&self, idx: usize) -> *mut V {
+        self.vals.ptr().add(idx)
+    }
+}
+
+impl<K, V> Drop for SoASlots<K, V> {
+    fn drop(&mut self) {
+        if mem::needs_drop::<(K, V)>() {
+            drop_entries(self);
+        }
+    }
+}
+
+/// Each key stored next to its value, so a successful lookup usually touches a single cache line. Probing past other
+/// keys is slower, since their values sit between them.
+pub struct AoS;
+
+impl Layout for AoS {
+    type Storage<K, V> = AoSSlots<K, V>;
+}
+
+pub struct AoSSlots<K, V> {
+    occupied: Occupancy,
+    entries: Buf<(K, V)>, // Only initialised where the slot is occupied
+    cap: usize,
+}
+
+impl<K, V> Slots for AoSSlots<K, V> {
+    type Key = K;
+    type Val = V;
+
+    #[inline(always)]
+    fn cap(&self) -> usize {
+        self.cap
+    }
+
+    #[inline(always)]
+    fn key(&self, idx: usize) -> Option<&K> {
+        if self.occupied.get(idx) {
+            Some(unsafe { &self.entries.get_ref(idx).0 })
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn take(&mut self, idx: usize) -> (K, V) {
+        self.occupied.unset(idx);
+        self.entries.get(idx)
+    }
+
+    #[inline(always)]
+    unsafe fn put(&mut self, idx: usize, key: K, val: V) {
+        self.occupied.set(idx);
+        self.entries.set(idx, (key, val));
+    }
+
+    #[inline(always)]
+    unsafe fn swap(&mut self, idx: usize, key: &mut K, val: &mut V) {
+        let entry = self.entries.get_mut(idx);
+        mem::swap(key, &mut entry.0);
+        mem::swap(val, &mut entry.1);
+    }
+
+    #[inline(always)]
+    unsafe fn shift(&mut self, from: usize, to: usize) {
+        self.occupied.unset(from);
+        self.occupied.set(to);
+        self.entries.set(to, self.entries.get(from));
+    }
+}
+
+impl<K, V> Storage for AoSSlots<K, V> {
+    const EMPTY: Self = Self {
+        occupied: Occupancy::EMPTY,
+        entries: Buf::EMPTY,
+        cap: 0,
+    };
+
+    fn with_capacity(cap: usize) -> Self {
+        Self {
+            occupied: Occupancy::with_capacity(cap),
+            entries: Buf::with_capacity(cap),
+            cap,
+        }
+    }
+
+    fn clone_slots_from(&mut self, source: &Self) where K: Clone, V: Clone {
+        prepare_clone(self, source);
+        if <(K, V)>::is_copy() {
+            self.occupied.words.copy_from(&source.occupied.words);
+            self.entries.copy_from(&source.entries);
+        } else {
+            clone_entries(self, source);
+        }
+    }
+
+    #[inline(always)]
+    fn next_occupied(&self, from: usize) -> Option<usize> {
+        self.occupied.next(from, self.cap)
+    }
+
+    #[inline(always)]
+    fn prefetch(&self, idx: usize) {
+        self.occupied.prefetch(idx);
+        prefetch((self.entries.ptr() as *const (K, V)).wrapping_add(idx));
+    }
+
+    #[inline(always)]
+    unsafe fn key_ref(&self, idx: usize) -> &K {
+        &self.entries.get_ref(idx).0
+    }
+
+    #[inline(always)]
+    unsafe fn val_ref(&self, idx: usize) -> &V {
+        &self.entries.get_ref(idx).1
+    }
+
+    #[inline(always)]
+    unsafe fn key_ptr(&self, idx: usize) -> *mut K {
+        // Only the address is taken, so no reference to the entry is made
+        ptr::addr_of_mut!((*self.entries.ptr().add(idx)).0)
+    }
+
+    #[inline(always)]
+    unsafe fn val_ptr(&self, idx: usize) -> *mut V {
+        ptr::addr_of_mut!((*self.entries.ptr().add(idx)).1)
+    }
+}
+
+impl<K, V> Drop for AoSSlots<K, V> {
+    fn drop(&mut self) {
+        if mem::needs_drop::<(K, V)>() {
+            drop_entries(self);
+        }
+    }
+}
+
+// The number of slots in each group of a `CacheLine` layout
+const GROUP_LEN: usize = 8;
+
+#[repr(C)]
+struct Group<K, V> {
+    occupied: u8, // One bit per slot
+    keys: [MaybeUninit<K>; GROUP_LEN],
+    vals: [MaybeUninit<V>; GROUP_LEN],
+}
+
+/// Slots in groups of eight, each group holding a byte that records which of its slots are occupied followed by its
+/// keys and then its values. Probing scans keys that are packed together as in `SoA`, while a key's value and the
+/// metadata for its slot are usually no more than a cache line away.
+pub struct CacheLine;
+
+impl Layout for CacheLine {
+    type Storage<K, V> = CacheLineSlots<K, V>;
+}
+
+pub struct CacheLineSlots<K, V> {
+    groups: Buf<Group<K, V>>,
+    cap: usize,
+}
+
+impl<K, V> CacheLineSlots<K, V> {
+    #[inline(always)]
+    fn group_count(cap: usize) -> usize {
+        cap.div_ceil(GROUP_LEN)
+    }
+
+    // Pointers are used rather than references to whole groups, since other slots in a group may be borrowed
+
+    #[inline(always)]
+    fn group(&self, idx: usize) -> (*mut Group<K, V>, usize) {
+        (unsafe { self.groups.ptr().add(idx / GROUP_LEN) }, idx % GROUP_LEN)
+    }
+
+    #[inline(always)]
+    fn occupied(&self, idx: usize) -> (*mut u8, u8) {
+        let (group, i) = self.group(idx);
+        (unsafe { ptr::addr_of_mut!((*group).occupied) }, 1 << i)
+    }
+
+}
+
+impl<K, V> Slots for CacheLineSlots<K, V> {
+    type Key = K;
+    type Val = V;
+
+    #[inline(always)]
+    fn cap(&self) -> usize {
+        self.cap
+    }
+
+    #[inline(always)]
+    fn key(&self, idx: usize) -> Option<&K> {
+        let (occupied, bit) = self.occupied(idx);
+        if unsafe { *occupied } & bit != 0 {
+            Some(unsafe { self.key_ref(idx) })
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn take(&mut self, idx: usize) -> (K, V) {
+        let (occupied, bit) = self.occupied(idx);
+        *occupied &= !bit;
+        (ptr::read(self.key_ptr(idx)), ptr::read(self.val_ptr(idx)))
+    }
+
+    #[inline(always)]
+    unsafe fn put(&mut self, idx: usize, key: K, val: V) {
+        let (occupied, bit) = self.occupied(idx);
+        *occupied |= bit;
+        ptr::write(self.key_ptr(idx), key);
+        ptr::write(self.val_ptr(idx), val);
+    }
+
+    #[inline(always)]
+    unsafe fn swap(&mut self, idx: usize, key: &mut K, val: &mut V) {
+        ptr::swap(key, self.key_ptr(idx));
+        ptr::swap(val, self.val_ptr(idx));
+    }
+
+    #[inline(always)]
+    unsafe fn shift(&mut self, from: usize, to: usize) {
+        let (key, val) = self.take(from);
+        self.put(to, key, val);
+    }
+}
+
+impl<K, V> Storage for CacheLineSlots<K, V> {
+    const EMPTY: Self = Self { groups: Buf::EMPTY, cap: 0 };
+
+    fn with_capacity(cap: usize) -> Self {
+        // A zeroed group has no occupied slots
+        Self { groups: Buf::with_capacity_zeroed(Self::group_count(cap)), cap }
+    }
+
+    fn clone_slots_from(&mut self, source: &Self) where K: Clone, V: Clone {
+        prepare_clone(self, source);
+        if <(K, V)>::is_copy() {
+            // Each group's occupancy byte is copied along with its keys and values
+            self.groups.copy_from(&source.groups);
+        } else {
+            clone_entries(self, source);
+        }
+    }
+
+    #[inline(always)]
+    fn next_occupied(&self, from: usize) -> Option<usize> {
+        // Skip a group at a time
+        let mut idx = from;
+        while idx < self.cap {
+            let (occupied, _) = self.occupied(idx);
+            let bits = unsafe { *occupied } >> (idx % GROUP_LEN);
+            if bits != 0 {
+                return Some(idx + bits.trailing_zeros() as usize);
+            }
+            idx = (idx / GROUP_LEN + 1) * GROUP_LEN;
+        }
+        None
+    }
+
+    #[inline(always)]
+    fn prefetch(&self, idx: usize) {
+        // Groups are not aligned to cache lines and a group's keys may span several, so the slot's key is not
+        // necessarily on the same line as the group's occupancy byte
+        prefetch(self.occupied(idx).0);
+        prefetch(unsafe { self.key_ptr(idx) });
+    }
+
+    #[inline(always)]
+    unsafe fn key_ref(&self, idx: usize) -> &K {
+        &*self.key_ptr(idx)
+    }
+
+    #[inline(always)]
+    unsafe fn val_ref(&self, idx: usize) -> &V {
+        &*self.val_ptr(idx)
+    }
+
+    #[inline(always)]
+    unsafe fn key_ptr(&self, idx: usize) -> *mut K {
+        let (group, i) = self.group(idx);
+        (ptr::addr_of_mut!((*group).keys) as *mut K).add(i)
+    }
+
+    #[inline(always)]
+    unsafe fn val_ptr(&self, idx: usize) -> *mut V {
+        let (group, i) = self.group(idx);
+        (ptr::addr_of_mut!((*group).vals) as *mut V).add(i)
+    }
+}
+
+impl<K, V> Drop for CacheLineSlots<K, V> {
+    fn drop(&mut self) {
+        if mem::needs_drop::<(K, V)>() {
+            drop_entries(self);
+        }
+    }
+}
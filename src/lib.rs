@@ -1,9 +1,10 @@
-#![feature(self_struct_ctor, trait_alias)]
+#![feature(self_struct_ctor, min_specialization, rustc_attrs, trait_alias)]
+// `rustc_attrs` is only used for `#[rustc_specialization_trait]`, which makes specializing on `TrivialClone` sound
+#![allow(internal_features)]
 
 // Standard
 use core::{
     hash::{BuildHasher, Hash, Hasher},
-    marker::PhantomData,
     ptr,
 };
 
@@ -15,6 +16,84 @@ use allocator_api::{
 };
 use packed_simd::u8x32;
 
+use crate::{
+    layout::{Layout, SoA, Storage},
+    probe::{ProbeStrategy, RobinHood},
+    raw::{Bucket, RawTable, StoredKey},
+};
+
+mod hasher;
+pub mod layout;
+pub mod map_like;
+pub mod probe;
+pub mod raw;
+mod robin_hood;
+
+// Containers
+pub mod anymap;
+pub mod array;
+pub mod bimap;
+pub mod counter;
+pub mod cow;
+pub mod expiring;
+pub mod frozen;
+pub mod hashcons;
+pub mod indexed;
+pub mod interner;
+pub mod multimap;
+pub mod small;
+pub mod stable;
+pub mod table;
+
+pub use crate::{
+    anymap::{AnyMap, SendSyncAnyMap},
+    array::{ArrayHashMap, CapacityFull},
+    bimap::{BiMap, Overwritten},
+    counter::Counter,
+    cow::CowHashMap,
+    expiring::{Clock, ExpiringMap, ManualClock, SystemClock},
+    frozen::FrozenMap,
+    hashcons::{HashCons, SyncHashCons},
+    indexed::{IndexedCollection, MultiIndex, RecordId, UniqueIndex, UniqueViolation},
+    interner::{Interner, Symbol, SyncInterner},
+    map_like::MapLike,
+    multimap::MultiMap,
+    small::SmallHashMap,
+    stable::StableHashMap,
+    table::HashTable,
+};
+
+/// Create a `HashMap` with the default hasher, probing strategy and layout from a list of entries, sized to hold them
+/// all without growing.
+///
+/// ```
+/// let map = smash::smash_map! { "one" => 1, "two" => 2 };
+/// assert_eq!(map.get(&"two"), Some(&2));
+/// ```
+#[macro_export]
+macro_rules! smash_map {
+    (@unit $x:tt) => { () };
+    ($($key:expr => $val:expr),* $(,)?) => {{
+        let len = <[()]>::len(&[$($crate::smash_map!(@unit $key)),*]);
+        let mut map = <$crate::HashMap<_, _>>::with_capacity(len);
+        $(map.insert($key, $val);)*
+        map
+    }};
+}
+
+/// Create a set, which is a `HashMap` whose values are all `()`, from a list of keys in the same way as `smash_map!`.
+///
+/// ```
+/// let set = smash::smash_set! { 'a', 'b', 'c' };
+/// assert!(set.contains_key(&'b'));
+/// ```
+#[macro_export]
+macro_rules! smash_set {
+    ($($key:expr),* $(,)?) => {
+        $crate::smash_map! { $($key => ()),* }
+    };
+}
+
 trait RawVecGetSet<T> {
     unsafe fn get(&self, idx: usize) -> T;
     unsafe fn set(&self, idx: usize, val: T);
@@ -40,20 +119,54 @@ impl<T> RawVecGetSet<T> for RawVec<T> {
     }
 }
 
-pub struct HashMap<K: Hash + Eq, V, S: BuildHasher + Default = FxBuildHasher> {
+/// A direct reference to an entry in a `HashMap`, allowing it to be accessed again without hashing its key.
+///
+/// A handle is invalidated when its entry moves (due to Robin Hood displacement, backward shifting or resizing) or is
+/// removed, but not when other entries do. Invalid handles are always detected, as are handles from another map
+/// (including a clone of this one): operations on them fail rather than reaching another entry. A fresh handle can be
+/// obtained with `HashMap::handle`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Handle(Bucket);
+
+// The number of keys that the batched operations hash and prefetch before probing any of them. Enough to cover the
+// latency of a miss, but few enough that the prefetched lines are still cached when they are probed.
+const BATCH_LEN: usize = 32;
+
+/// A hash map, generic over the `ProbeStrategy` used to place its keys and the `Layout` of its slots in memory. The
+/// defaults, `RobinHood` and `SoA`, are a good choice for most workloads, but the others can be swapped in to compare
+/// them.
+pub struct HashMap<K, V, S = FxBuildHasher, P: ProbeStrategy = RobinHood, L: Layout = SoA> {
     //tags: RawVec<u8x32>,
-    keys: RawVec<Option<K>>,
-    vals: RawVec<V>,
-
-    len: usize, // Always <= cap
-    cap: usize, // Always 2^n
+    table: RawTable<K, V, P, L>,
 
     hasher: S,
+}
+
+impl<K, V, S, P: ProbeStrategy, L: Layout> HashMap<K, V, S, P, L> {
+    /// Create an empty map that uses the given hasher. This allocates nothing and can be used in a const context, such as
+    /// the initialiser of a `static`.
+    pub const fn with_hasher(hasher: S) -> Self {
+        Self {
+            table: RawTable::new(),
+
+            hasher,
+        }
+    }
+}
 
-    _phantom: PhantomData<K>,
+impl<K: Hash + Eq, V, S: BuildHasher + Default, P: ProbeStrategy, L: Layout> HashMap<K, V, S, P, L> {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_hasher(Default::default())
+    }
+
+    #[inline(always)]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, Default::default())
+    }
 }
 
-impl<K: Hash + Eq, V, S: BuildHasher + Default> HashMap<K, V, S> {
+impl<K: Hash + Eq, V, S: BuildHasher, P: ProbeStrategy, L: Layout> HashMap<K, V, S, P, L> {
     // Private interface
 
     #[inline(always)]
@@ -62,146 +175,136 @@ impl<K: Hash + Eq, V, S: BuildHasher + Default> HashMap<K, V, S> {
     }
 
     #[inline(always)]
-    fn idx_for(key: &K, cap: usize, hasher: &S) -> usize {
+    fn hash_of(key: &K, hasher: &S) -> u64 {
         let mut hasher = hasher.build_hasher();
         key.hash(&mut hasher);
-        hasher.finish() as usize & cap.wrapping_sub(1)
+        hasher.finish()
     }
 
+    // The key in slot `idx`, if it is occupied
     #[inline(always)]
-    fn resize_to(&mut self, new_cap: usize) {
-        assert!(new_cap.is_power_of_two());
-        assert!(new_cap >= self.len);
-
-        let mut new_keys = RawVec::with_capacity(new_cap);
-        let mut new_vals = RawVec::with_capacity(new_cap);
+    fn key(&self, idx: usize) -> Option<&K> {
+        self.table.key(idx)
+    }
 
-        for new_idx in 0..new_cap {
-            unsafe { new_keys.set(new_idx, None) };
-        }
+    // The following require that `idx` is occupied
 
-        // For each value in the existing map
-        for idx in 0..self.cap {
-            if let Some(mut key) = unsafe { self.keys.get(idx) } {
-                let mut val = unsafe { self.vals.get(idx) };
-
-                // Find free index
-                let mut intended_idx = Self::idx_for(&key, new_cap, &self.hasher);
-                let mut new_idx = intended_idx;
-                loop {
-                    match unsafe { new_keys.get_mut(new_idx) } {
-                        None => break,
-                        Some(k) => { // Robin Hood swapping
-                            let other_intended_idx = Self::idx_for(k, new_cap, &self.hasher);
-                            if (new_cap + intended_idx - other_intended_idx) & new_cap.wrapping_sub(1) >= new_cap / 2 {
-                               std::mem::swap(&mut key, k);
-                               std::mem::swap(&mut val, unsafe { new_vals.get_mut(new_idx) });
-                               intended_idx = other_intended_idx;
-                            }
-                        },
-                    }
-                    new_idx = (new_idx + 1) & new_cap.wrapping_sub(1);
-                }
-
-                // Write key and value
-                unsafe {
-                    new_keys.set(new_idx, Some(key));
-                    new_vals.set(new_idx, val);
-                }
-            }
-        }
+    #[inline(always)]
+    unsafe fn key_ref(&self, idx: usize) -> &K {
+        self.table.key_ref(idx)
+    }
 
-        self.keys = new_keys;
-        self.vals = new_vals;
-        self.cap = new_cap;
+    #[inline(always)]
+    unsafe fn key_mut(&mut self, idx: usize) -> &mut K {
+        &mut *self.table.key_ptr(idx)
     }
 
     #[inline(always)]
-    fn try_grow(&mut self) {
-        // Only grow if len == capacity
-        if self.len < self.cap {
-            return;
-        }
+    unsafe fn val_ref(&self, idx: usize) -> &V {
+        self.table.val_ref(idx)
+    }
 
-        if self.cap == 0 {
-            self.keys = RawVec::with_capacity(1);
-            self.vals = RawVec::with_capacity(1);
-            self.cap = 1;
-            return;
-        }
+    #[inline(always)]
+    unsafe fn val_mut(&mut self, idx: usize) -> &mut V {
+        &mut *self.table.val_ptr(idx)
+    }
 
-        self.resize_to(2 * self.cap);
+    #[inline(always)]
+    fn try_grow(&mut self) -> bool {
+        self.table.try_grow()
     }
 
     #[inline(always)]
     fn try_shrink(&mut self) {
-        // Only shrink if len <= quarter of capacity
-        if self.len > self.cap / 4 {
-            return;
-        }
-
-        self.resize_to(self.cap / 2);
+        self.table.try_shrink();
     }
 
     #[inline(always)]
     fn get_idx(&self, key: &K) -> Option<usize> {
-        let intended_idx = Self::idx_for(key, self.cap, &self.hasher);
-        let mut idx = intended_idx;
-        for _ in 0..self.cap {
-            match unsafe { self.keys.get_ref(idx) } {
-                Some(k) if k.eq(key) => return Some(idx),
-                Some(k) if (self.cap + intended_idx - Self::idx_for(k, self.cap, &self.hasher)) & self.cap.wrapping_sub(1) > self.cap / 2 => return None,
-                _ => {},
-            }
+        self.find_idx(Self::hash_of(key, &self.hasher), |k| k.eq(key))
+    }
 
-            idx = (idx + 1) & self.cap.wrapping_sub(1);
+    // Find the index of the key that has the given hash and satisfies `eq`
+    #[inline(always)]
+    fn find_idx(&self, hash: u64, eq: impl FnMut(&K) -> bool) -> Option<usize> {
+        self.probe_idx(hash, eq).ok()
+    }
+
+    // Find the indices of several keys, or `None` if any of them is missing
+    fn get_many_idx<const N: usize>(&self, keys: [&K; N]) -> Option<[usize; N]> {
+        let mut idxs = [0; N];
+        for (idx, key) in idxs.iter_mut().zip(keys.iter()) {
+            *idx = self.get_idx(key)?;
         }
-        None
+        Some(idxs)
     }
 
-    // Public interface
+    #[inline(always)]
+    fn handle_idx(&self, handle: Handle) -> Option<usize> {
+        self.table.bucket_idx(handle.0)
+    }
 
+    // Find either the index of the key that has the given hash and satisfies `eq` or, if there is no such key, a hint
+    // for `insert_at`
     #[inline(always)]
-    pub fn new() -> Self {
-        Self {
-            keys: RawVec::new(),
-            vals: RawVec::new(),
+    fn probe_idx(&self, hash: u64, eq: impl FnMut(&K) -> bool) -> Result<usize, usize> {
+        self.table.probe(hash, eq)
+    }
 
-            len: 0,
-            cap: 0,
+    // Insert a key that is known to be absent, using the hint returned by `probe_idx`, growing the table if the strategy
+    // finds no room for it. Returns the index of the new entry.
+    #[inline(always)]
+    fn insert_at(&mut self, hint: usize, hash: u64, key: K, val: V) -> usize {
+        self.table.insert_at(hint, hash, key, val)
+    }
 
-            hasher: Default::default(),
+    // Insert an entry whose hash is already known
+    #[inline(always)]
+    fn insert_hashed(&mut self, hash: u64, key: K, mut val: V) -> Option<V> {
+        self.try_grow();
 
-            _phantom: PhantomData,
+        match self.probe_idx(hash, |k| k.eq(&key)) {
+            Ok(idx) => {
+                std::mem::swap(&mut val, unsafe { self.val_mut(idx) });
+                Some(val)
+            },
+            Err(hint) => {
+                self.insert_at(hint, hash, key, val);
+                None
+            },
         }
     }
 
+    // Hash a batch of keys, prefetching the slot that each will be probed from
     #[inline(always)]
-    pub fn with_capacity(capacity: usize) -> Self {
-        Self::with_capacity_and_hasher(capacity, Default::default())
+    fn hash_batch<'a>(&self, keys: impl Iterator<Item = &'a K>, hashes: &mut [u64; BATCH_LEN]) where K: 'a {
+        for (key, hash) in keys.zip(hashes.iter_mut()) {
+            *hash = Self::hash_of(key, &self.hasher);
+            self.table.prefetch(*hash);
+        }
     }
 
     #[inline(always)]
-    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
-        let cap = capacity.next_power_of_two();
+    fn remove_idx(&mut self, idx: usize) -> (K, V) {
+        let entry = self.table.remove_at(idx);
+        self.try_shrink();
+        entry
+    }
 
-        let keys = RawVec::with_capacity(cap);
+    // Move every entry out, leaving the map empty but keeping its capacity
+    fn take_entries(&mut self) -> Vec<(K, V)> {
+        self.table.take_entries()
+    }
 
-        for idx in 0..cap {
-            unsafe { keys.set(idx, None) };
-        }
+    // Public interface
 
+    #[inline(always)]
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
         Self {
             //tags: RawVec::with_capacity(Self::tag_block_count(cap)),
-            keys,
-            vals: RawVec::with_capacity(cap),
-
-            len: 0,
-            cap,
+            table: RawTable::with_capacity(capacity),
 
             hasher,
-
-            _phantom: PhantomData,
         }
     }
 
@@ -210,13 +313,11 @@ impl<K: Hash + Eq, V, S: BuildHasher + Default> HashMap<K, V, S> {
     }
 
     pub fn capacity(&self) -> usize {
-        self.cap
+        self.table.capacity()
     }
 
     pub fn reserve(&mut self, additional: usize) {
-        if additional > (self.cap - self.len) {
-            self.resize_to((self.len + additional).next_power_of_two());
-        }
+        self.table.reserve(additional);
     }
 
     pub fn try_reserve(&mut self, additional: usize) -> Result<(), CollectionAllocErr> {
@@ -225,87 +326,87 @@ impl<K: Hash + Eq, V, S: BuildHasher + Default> HashMap<K, V, S> {
     }
 
     pub fn shrink_to_fit(&mut self) {
-        self.resize_to(self.len.next_power_of_two());
+        self.table.shrink_to(0);
     }
 
     pub fn shrink_to(&mut self, min_capacity: usize) {
-        if self.cap < min_capacity {
+        if self.capacity() < min_capacity {
             panic!("Current capacity is smaller than supplied minimum capacity");
         }
 
-        self.resize_to(self.len.next_power_of_two().max(min_capacity.next_power_of_two()));
+        self.table.shrink_to(min_capacity);
     }
 
-    pub fn keys(&self) -> Keys<K, V> {
+    pub fn keys(&self) -> Keys<K, V, L> {
         Keys {
-            keys: &self.keys,
-            vals: &self.vals,
+            slots: &self.table.slots,
             idx: 0,
         }
     }
 
-    pub fn values(&self) -> Values<K, V> {
+    pub fn values(&self) -> Values<K, V, L> {
         Values {
-            keys: &self.keys,
-            vals: &self.vals,
+            slots: &self.table.slots,
             idx: 0,
         }
     }
 
-    pub fn values_mut(&mut self) -> ValuesMut<K, V> {
+    pub fn values_mut(&mut self) -> ValuesMut<K, V, L> {
         ValuesMut {
-            keys: &self.keys,
-            vals: &self.vals,
+            slots: &self.table.slots,
             idx: 0,
         }
     }
 
-    pub fn iter(&self) -> Iter<K, V> {
+    pub fn iter(&self) -> Iter<K, V, L> {
         Iter {
-            keys: &self.keys,
-            vals: &self.vals,
+            slots: &self.table.slots,
             idx: 0,
         }
     }
 
-    pub fn iter_mut(&self) -> IterMut<K, V> {
+    pub fn iter_mut(&mut self) -> IterMut<K, V, L> {
         IterMut {
-            keys: &self.keys,
-            vals: &self.vals,
+            slots: &self.table.slots,
             idx: 0,
         }
     }
 
+    /// A cursor over the entries of the map, starting at the first. Unlike `retain`, the cursor can hand back the
+    /// entries that it removes and may be abandoned part way through.
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, K, V, S, P, L> {
+        let idx = self.table.next_occupied(0).unwrap_or(self.table.cap);
+        CursorMut {
+            end: self.table.cap,
+            map: self,
+            idx,
+            removed: false,
+        }
+    }
+
     pub fn len(&self) -> usize {
-        self.len
+        self.table.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.len == 0
+        self.table.is_empty()
     }
 
     // TODO: Drain
 
     pub fn clear(&mut self) {
-        for idx in 0..self.cap {
-            if let Some(key) = unsafe { self.keys.get_mut(idx) } {
-                drop(key);
-                drop(unsafe { self.vals.get_mut(idx) });
-                unsafe { self.keys.set(idx, None) };
-            }
-        }
-        self.len = 0;
+        self.table.clear();
     }
 
     pub fn get(&self, key: &K) -> Option<&V> {
         self.get_idx(key).map(|idx| {
-            unsafe { self.vals.get_ref(idx) }
+            unsafe { self.val_ref(idx) }
         })
     }
 
     pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
         self.get_idx(key).map(|idx| {
-            unsafe { (self.keys.get_ref(idx).as_ref().unwrap(), self.vals.get_ref(idx)) }
+            unsafe { (self.key_ref(idx), self.val_ref(idx)) }
         })
     }
 
@@ -315,227 +416,366 @@ impl<K: Hash + Eq, V, S: BuildHasher + Default> HashMap<K, V, S> {
 
     pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
         if let Some(idx) = self.get_idx(key) {
-            Some(unsafe { self.vals.get_mut(idx) })
+            Some(unsafe { self.val_mut(idx) })
         } else {
             None
         }
     }
 
-    pub fn insert(&mut self, mut key: K, mut val: V) -> Option<V> {
-        self.try_grow();
-
-        // Find free index
-        let mut intended_idx = Self::idx_for(&key, self.cap, &self.hasher);
-        let mut idx = intended_idx;
-        for _ in 0..self.cap {
-            match unsafe { self.keys.get_mut(idx) } {
-                None => break,
-                Some(k) if (*k).eq(&key) => {
-                    std::mem::swap(&mut val, unsafe { self.vals.get_mut(idx) });
-                    return Some(val);
-                },
-                Some(k) => { // Robin Hood swapping
-                    let other_intended_idx = Self::idx_for(k, self.cap, &self.hasher);
-                    if (self.cap + intended_idx - other_intended_idx) & self.cap.wrapping_sub(1) > self.cap / 2 {
-                        std::mem::swap(&mut key, k);
-                        std::mem::swap(&mut val, unsafe { self.vals.get_mut(idx) });
-                        intended_idx = other_intended_idx;
-                    }
-                },
+    /// Get mutable references to the values of several keys at once. Returns `None` if any key is missing or if any two
+    /// keys refer to the same entry.
+    pub fn get_many_mut<const N: usize>(&mut self, keys: [&K; N]) -> Option<[&mut V; N]> {
+        let idxs = self.get_many_idx(keys)?;
+        for (i, idx) in idxs.iter().enumerate() {
+            if idxs[..i].contains(idx) {
+                return None;
             }
-            idx = (idx + 1) & self.cap.wrapping_sub(1);
         }
+        let table = &self.table;
+        Some(core::array::from_fn(move |i| unsafe { &mut *table.val_ptr(idxs[i]) }))
+    }
 
-        // Write key and value
-        unsafe {
-            self.keys.set(idx, Some(key));
-            self.vals.set(idx, val);
-        }
+    /// Like `get_many_mut`, but without checking that the keys refer to distinct entries.
+    ///
+    /// # Safety
+    ///
+    /// No two of the keys may be equal, otherwise aliasing mutable references are created.
+    pub unsafe fn get_many_unchecked_mut<const N: usize>(&mut self, keys: [&K; N]) -> Option<[&mut V; N]> {
+        let idxs = self.get_many_idx(keys)?;
+        let table = &self.table;
+        Some(core::array::from_fn(move |i| &mut *table.val_ptr(idxs[i])))
+    }
 
-        self.len += 1;
+    pub fn insert_with_handle(&mut self, key: K, mut val: V) -> (Handle, Option<V>) {
+        self.try_grow();
 
-        None
+        let hash = Self::hash_of(&key, &self.hasher);
+        let (idx, old) = match self.probe_idx(hash, |k| k.eq(&key)) {
+            Ok(idx) => {
+                std::mem::swap(&mut val, unsafe { self.val_mut(idx) });
+                (idx, Some(val))
+            },
+            Err(hint) => (self.insert_at(hint, hash, key, val), None),
+        };
+        (Handle(self.table.bucket(idx)), old)
     }
 
-    pub fn remove(&mut self, key: &K) -> Option<V> {
-        if let Some(idx) = self.get_idx(key) {
-            let mut old_key = None;
-            std::mem::swap(unsafe { self.keys.get_mut(idx) }, &mut old_key);
-            self.try_shrink();
-            Some(unsafe { self.vals.get(idx) })
+    pub fn handle(&self, key: &K) -> Option<Handle> {
+        self.get_idx(key).map(|idx| Handle(self.table.bucket(idx)))
+    }
+
+    pub fn is_valid_handle(&self, handle: Handle) -> bool {
+        self.handle_idx(handle).is_some()
+    }
+
+    pub fn get_by_handle(&self, handle: Handle) -> Option<&V> {
+        self.handle_idx(handle).map(|idx| unsafe { self.val_ref(idx) })
+    }
+
+    pub fn get_key_value_by_handle(&self, handle: Handle) -> Option<(&K, &V)> {
+        self.handle_idx(handle).map(|idx| {
+            unsafe { (self.key_ref(idx), self.val_ref(idx)) }
+        })
+    }
+
+    pub fn get_mut_by_handle(&mut self, handle: Handle) -> Option<&mut V> {
+        if let Some(idx) = self.handle_idx(handle) {
+            Some(unsafe { self.val_mut(idx) })
         } else {
             None
         }
     }
 
+    pub fn remove_by_handle(&mut self, handle: Handle) -> Option<(K, V)> {
+        self.handle_idx(handle).map(|idx| self.remove_idx(idx))
+    }
+
+    pub fn insert(&mut self, key: K, val: V) -> Option<V> {
+        let hash = Self::hash_of(&key, &self.hasher);
+        self.insert_hashed(hash, key, val)
+    }
+
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, f: F) -> &mut V {
+        self.try_grow();
+
+        let hash = Self::hash_of(&key, &self.hasher);
+        let idx = match self.probe_idx(hash, |k| k.eq(&key)) {
+            Ok(idx) => idx,
+            Err(hint) => self.insert_at(hint, hash, key, f()),
+        };
+        unsafe { self.val_mut(idx) }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.get_idx(key).map(|idx| self.remove_idx(idx).1)
+    }
+
     pub fn remove_entry(&mut self, key: &K) -> Option<(K, V)> {
-        if let Some(idx) = self.get_idx(key) {
-            let mut old_key = None;
-            std::mem::swap(unsafe { self.keys.get_mut(idx) }, &mut old_key);
-            self.try_shrink();
-            Some((old_key.unwrap(), unsafe { self.vals.get(idx) }))
-        } else {
-            None
-        }
+        self.get_idx(key).map(|idx| self.remove_idx(idx))
     }
 
-    // TODO: retain()
-}
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut f: F) {
+        self.table.retain(|k, v| f(k, v));
+    }
 
-impl<K: Hash + Eq, V, S: BuildHasher + Default> Drop for HashMap<K, V, S> {
-    fn drop(&mut self) {
-        for idx in 0..self.cap {
-            if let Some(key) = unsafe { self.keys.get_mut(idx) } {
-                drop(key);
-                drop(unsafe { self.vals.get_mut(idx) });
+    /// Look up a batch of keys, returning their values in the same order.
+    ///
+    /// Keys are hashed and their slots prefetched a few dozen at a time before any of them are probed, which hides much
+    /// of the latency of lookups in tables that are larger than the cache.
+    pub fn get_many(&self, keys: &[K]) -> Vec<Option<&V>> {
+        let mut vals = Vec::with_capacity(keys.len());
+        let mut hashes = [0; BATCH_LEN];
+        for batch in keys.chunks(BATCH_LEN) {
+            self.hash_batch(batch.iter(), &mut hashes);
+            for (key, hash) in batch.iter().zip(&hashes) {
+                vals.push(self.find_idx(*hash, |k| k.eq(key)).map(|idx| unsafe { self.val_ref(idx) }));
             }
         }
+        vals
     }
-}
 
-impl<K: Hash + Eq + Clone, V: Clone, S: BuildHasher + Clone + Default> Clone for HashMap<K, V, S> {
-    fn clone(&self) -> Self {
-        //let mut tags = RawVec::with_capacity(Self::tag_block_count(self.cap));
-        let mut keys = RawVec::with_capacity(self.cap);
-        let mut vals = RawVec::with_capacity(self.cap);
+    /// Check whether each of a batch of keys is present, in the same way as `get_many`.
+    pub fn contains_many(&self, keys: &[K]) -> Vec<bool> {
+        let mut found = Vec::with_capacity(keys.len());
+        let mut hashes = [0; BATCH_LEN];
+        for batch in keys.chunks(BATCH_LEN) {
+            self.hash_batch(batch.iter(), &mut hashes);
+            for (key, hash) in batch.iter().zip(&hashes) {
+                found.push(self.find_idx(*hash, |k| k.eq(key)).is_some());
+            }
+        }
+        found
+    }
 
-        //for idx in 0..self.tags.cap() {
-        //    unsafe { tags.set(idx, self.tags.get(idx)) };
-        //}
+    /// Insert a batch of entries in the same way as `get_many`, returning the values that they replaced in order.
+    pub fn insert_many<I: IntoIterator<Item = (K, V)>>(&mut self, entries: I) -> Vec<Option<V>> {
+        let mut entries = entries.into_iter();
 
-        for idx in 0..self.cap {
-            unsafe { keys.set(idx, None) };
+        let mut old = Vec::with_capacity(entries.size_hint().0);
+        let mut batch = Vec::with_capacity(BATCH_LEN);
+        let mut hashes = [0; BATCH_LEN];
+        loop {
+            batch.extend(entries.by_ref().take(BATCH_LEN));
+            if batch.is_empty() {
+                return old;
+            }
+
+            // The table grows only as missing keys need room, like it does for `insert`. Growing moves every slot, so
+            // the rest of that batch's prefetches are wasted, but that happens too rarely to matter.
+            self.hash_batch(batch.iter().map(|(k, _)| k), &mut hashes);
+            for ((key, val), hash) in batch.drain(..).zip(&hashes) {
+                old.push(self.insert_hashed(*hash, key, val));
+            }
         }
+    }
 
-        for idx in 0..self.cap {
-            if let Some(key) = unsafe { self.keys.get_ref(idx) } {
-                unsafe { keys.set(idx, Some(key.clone())) };
-                unsafe { vals.set(idx, self.vals.get_ref(idx).clone()) };
+    /// Remove a batch of keys in the same way as `get_many`, returning their values in order.
+    pub fn remove_many(&mut self, keys: &[K]) -> Vec<Option<V>> {
+        let mut vals = Vec::with_capacity(keys.len());
+        let mut hashes = [0; BATCH_LEN];
+        for batch in keys.chunks(BATCH_LEN) {
+            self.hash_batch(batch.iter(), &mut hashes);
+            for (key, hash) in batch.iter().zip(&hashes) {
+                vals.push(self.find_idx(*hash, |k| k.eq(key)).map(|idx| self.remove_idx(idx).1));
             }
         }
+        vals
+    }
+}
 
-        Self {
-            //tags,
-            keys,
-            vals,
+impl<K: Hash + Eq, V, S: BuildHasher + Default, P: ProbeStrategy, L: Layout> Default for HashMap<K, V, S, P, L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-            len: self.len,
-            cap: self.cap,
+impl<K: Hash + Eq + Clone, V: Clone, S: BuildHasher + Clone, P: ProbeStrategy, L: Layout> Clone
+    for HashMap<K, V, S, P, L>
+{
+    fn clone(&self) -> Self {
+        Self {
+            table: self.table.clone(),
 
             hasher: self.hasher.clone(),
-
-            _phantom: PhantomData,
         }
     }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.table.clone_from(&source.table);
+        self.hasher.clone_from(&source.hasher);
+    }
 }
 
-pub struct Keys<'a, K, V> {
-    keys: &'a RawVec<Option<K>>,
-    vals: &'a RawVec<V>,
+pub struct Keys<'a, K: 'a, V: 'a, L: Layout = SoA> {
+    slots: &'a L::Storage<StoredKey<K>, V>,
     idx: usize,
 }
 
-impl<'a, K, V> Iterator for Keys<'a, K, V> {
+impl<'a, K: 'a, V: 'a, L: Layout> Iterator for Keys<'a, K, V, L> {
     type Item = &'a K;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while self.idx <= self.keys.cap() {
-            self.idx += 1;
-            if let Some(k) = unsafe { self.keys.get_ref(self.idx.wrapping_sub(1)) } {
-                return Some(k);
-            }
-        }
-
-        None
+        let idx = self.slots.next_occupied(self.idx)?;
+        self.idx = idx + 1;
+        Some(unsafe { &self.slots.key_ref(idx).key })
     }
 }
 
-pub struct Values<'a, K: 'a, V: 'a> {
-    keys: &'a RawVec<Option<K>>,
-    vals: &'a RawVec<V>,
+pub struct Values<'a, K: 'a, V: 'a, L: Layout = SoA> {
+    slots: &'a L::Storage<StoredKey<K>, V>,
     idx: usize,
 }
 
-impl<'a, K: 'a, V: 'a> Iterator for Values<'a, K, V> {
+impl<'a, K: 'a, V: 'a, L: Layout> Iterator for Values<'a, K, V, L> {
     type Item = &'a V;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while self.idx <= self.keys.cap() {
-            self.idx += 1;
-            if unsafe { self.keys.get_ref(self.idx.wrapping_sub(1)).is_some() } {
-                return Some(unsafe { self.vals.get_ref(self.idx.wrapping_sub(1)) });
-            }
-        }
-
-        None
+        let idx = self.slots.next_occupied(self.idx)?;
+        self.idx = idx + 1;
+        Some(unsafe { self.slots.val_ref(idx) })
     }
 }
 
-pub struct ValuesMut<'a, K: 'a, V: 'a> {
-    keys: &'a RawVec<Option<K>>,
-    vals: &'a RawVec<V>,
+pub struct ValuesMut<'a, K: 'a, V: 'a, L: Layout = SoA> {
+    slots: &'a L::Storage<StoredKey<K>, V>,
     idx: usize,
 }
 
-impl<'a, K: 'a, V: 'a> Iterator for ValuesMut<'a, K, V> {
+impl<'a, K: 'a, V: 'a, L: Layout> Iterator for ValuesMut<'a, K, V, L> {
     type Item = &'a mut V;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while self.idx <= self.keys.cap() {
-            self.idx += 1;
-            if unsafe { self.keys.get_ref(self.idx.wrapping_sub(1)).is_some() } {
-                return Some(unsafe { self.vals.get_mut(self.idx.wrapping_sub(1)) });
-            }
-        }
-
-        None
+        let idx = self.slots.next_occupied(self.idx)?;
+        self.idx = idx + 1;
+        Some(unsafe { &mut *self.slots.val_ptr(idx) })
     }
 }
 
-pub struct Iter<'a, K: 'a, V: 'a> {
-    keys: &'a RawVec<Option<K>>,
-    vals: &'a RawVec<V>,
+pub struct Iter<'a, K: 'a, V: 'a, L: Layout = SoA> {
+    slots: &'a L::Storage<StoredKey<K>, V>,
     idx: usize,
 }
 
-impl<'a, K: 'a, V: 'a> Iterator for Iter<'a, K, V> {
+impl<'a, K: 'a, V: 'a, L: Layout> Iterator for Iter<'a, K, V, L> {
     type Item = (&'a K, &'a V);
 
     fn next(&mut self) -> Option<Self::Item> {
-        while self.idx <= self.keys.cap() {
-            self.idx += 1;
-            if let Some(k) = unsafe { self.keys.get_ref(self.idx.wrapping_sub(1)) } {
-                return Some((
-                    k,
-                    unsafe { self.vals.get_ref(self.idx.wrapping_sub(1)) },
-                ));
-            }
-        }
-
-        None
+        let idx = self.slots.next_occupied(self.idx)?;
+        self.idx = idx + 1;
+        Some(unsafe { (&self.slots.key_ref(idx).key, self.slots.val_ref(idx)) })
     }
 }
 
-pub struct IterMut<'a, K: 'a, V: 'a> {
-    keys: &'a RawVec<Option<K>>,
-    vals: &'a RawVec<V>,
+pub struct IterMut<'a, K: 'a, V: 'a, L: Layout = SoA> {
+    slots: &'a L::Storage<StoredKey<K>, V>,
     idx: usize,
 }
 
-impl<'a, K: 'a, V: 'a> Iterator for IterMut<'a, K, V> {
+impl<'a, K: 'a, V: 'a, L: Layout> Iterator for IterMut<'a, K, V, L> {
     type Item = (&'a K, &'a mut V);
 
     fn next(&mut self) -> Option<Self::Item> {
-        while self.idx <= self.keys.cap() {
-            self.idx += 1;
-            if let Some(k) = unsafe { self.keys.get_ref(self.idx.wrapping_sub(1)) } {
-                return Some((
-                    k,
-                    unsafe { self.vals.get_mut(self.idx.wrapping_sub(1)) },
-                ));
-            }
+        let idx = self.slots.next_occupied(self.idx)?;
+        self.idx = idx + 1;
+        Some(unsafe { (&self.slots.key_ref(idx).key, &mut *self.slots.val_ptr(idx)) })
+    }
+}
+
+/// A cursor that walks the entries of a `HashMap` in slot order, allowing each to be modified or removed in place.
+///
+/// The map is not shrunk until the cursor is dropped, so removals never reorder the entries that are yet to be visited.
+pub struct CursorMut<
+    'a,
+    K: Hash + Eq + 'a,
+    V: 'a,
+    S: BuildHasher + 'a = FxBuildHasher,
+    P: ProbeStrategy + 'a = RobinHood,
+    L: Layout + 'a = SoA,
+> {
+    map: &'a mut HashMap<K, V, S, P, L>,
+    idx: usize, // The current entry, or `end` once the cursor has passed the last one
+    end: usize, // Entries at or beyond this slot have been shifted back around from the start of the table
+    removed: bool,
+}
+
+impl<'a, K: Hash + Eq + 'a, V: 'a, S: BuildHasher + 'a, P: ProbeStrategy + 'a, L: Layout + 'a>
+    CursorMut<'a, K, V, S, P, L>
+{
+    // Move to the first entry at or after `from`
+    #[inline(always)]
+    fn settle(&mut self, from: usize) {
+        self.idx = match self.map.table.next_occupied(from) {
+            Some(idx) if idx < self.end => idx,
+            _ => self.end,
+        };
+    }
+
+    pub fn key(&self) -> Option<&K> {
+        if self.idx < self.end {
+            Some(unsafe { self.map.key_ref(self.idx) })
+        } else {
+            None
         }
+    }
 
-        None
+    pub fn value_mut(&mut self) -> Option<&mut V> {
+        if self.idx < self.end {
+            Some(unsafe { self.map.val_mut(self.idx) })
+        } else {
+            None
+        }
+    }
+
+    /// Move to the next entry. Returns `false` if there are no more.
+    pub fn move_next(&mut self) -> bool {
+        if self.idx < self.end {
+            self.settle(self.idx + 1);
+        }
+        self.idx < self.end
+    }
+
+    /// Remove the current entry and move to the next.
+    pub fn remove_current(&mut self) -> Option<(K, V)> {
+        if self.idx >= self.end {
+            return None;
+        }
+
+        // Backward shifting fills the hole with the entries that follow it. If it reaches `end` then an entry that has
+        // already been visited (or wraps around from the start of the table) moves back into range, so exclude it.
+        let watch = self.end & self.map.table.cap.wrapping_sub(1);
+        let (entry, moved) = self.map.table.remove_at_watching(self.idx, watch);
+        if moved {
+            self.end -= 1;
+        }
+        self.removed = true;
+
+        // The next entry may have been shifted into the current slot
+        self.settle(self.idx);
+
+        Some(entry)
+    }
+
+    /// Move to the entry with the given key, from which the cursor continues to the end of the table. Returns `false`,
+    /// leaving the cursor where it was, if the key is not present or if removals have shifted its entry back around
+    /// from the start of the table, past the last entry that the cursor will visit.
+    pub fn seek(&mut self, key: &K) -> bool {
+        match self.map.get_idx(key) {
+            // The entries beyond `end` were visited at the start of the table, so it must stay where it is
+            Some(idx) if idx < self.end => {
+                self.idx = idx;
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+impl<'a, K: Hash + Eq + 'a, V: 'a, S: BuildHasher + 'a, P: ProbeStrategy + 'a, L: Layout + 'a> Drop
+    for CursorMut<'a, K, V, S, P, L>
+{
+    fn drop(&mut self) {
+        if self.removed {
+            self.map.try_shrink();
+        }
     }
 }
@@ -0,0 +1,120 @@
+use std::{
+    collections::hash_map,
+    hash::{BuildHasher, Hash},
+};
+
+use crate::{
+    HashMap,
+    Iter,
+    layout::Layout,
+    probe::ProbeStrategy,
+};
+
+/// The operations common to hash maps, so that code can be written once and run against `smash::HashMap`, the standard
+/// library's `HashMap` or, with the `hashbrown` feature, hashbrown's `HashMap`.
+pub trait MapLike<K, V> {
+    type Iter<'a>: Iterator<Item = (&'a K, &'a V)> where Self: 'a, K: 'a, V: 'a;
+
+    fn get(&self, key: &K) -> Option<&V>;
+
+    /// Insert an entry, returning the previous value.
+    fn insert(&mut self, key: K, val: V) -> Option<V>;
+
+    fn remove(&mut self, key: &K) -> Option<V>;
+
+    /// Get the value of a key, first inserting the result of `f` if it is not present.
+    fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, f: F) -> &mut V;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn iter(&self) -> Self::Iter<'_>;
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher, P: ProbeStrategy, L: Layout> MapLike<K, V> for HashMap<K, V, S, P, L> {
+    type Iter<'a> = Iter<'a, K, V, L> where Self: 'a, K: 'a, V: 'a;
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+
+    fn insert(&mut self, key: K, val: V) -> Option<V> {
+        self.insert(key, val)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(key)
+    }
+
+    fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, f: F) -> &mut V {
+        self.get_or_insert_with(key, f)
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> MapLike<K, V> for std::collections::HashMap<K, V, S> {
+    type Iter<'a> = hash_map::Iter<'a, K, V> where Self: 'a, K: 'a, V: 'a;
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+
+    fn insert(&mut self, key: K, val: V) -> Option<V> {
+        self.insert(key, val)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(key)
+    }
+
+    fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, f: F) -> &mut V {
+        self.entry(key).or_insert_with(f)
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+}
+
+#[cfg(feature = "hashbrown")]
+impl<K: Hash + Eq, V, S: BuildHasher> MapLike<K, V> for hashbrown::HashMap<K, V, S> {
+    type Iter<'a> = hashbrown::hash_map::Iter<'a, K, V> where Self: 'a, K: 'a, V: 'a;
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+
+    fn insert(&mut self, key: K, val: V) -> Option<V> {
+        self.insert(key, val)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(key)
+    }
+
+    fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, f: F) -> &mut V {
+        self.entry(key).or_insert_with(f)
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    hash::{BuildHasher, Hash},
+    mem, slice,
+};
+
+use fxhash::FxBuildHasher;
+
+use crate::HashMap;
+
+// The values for a single key. The first value lives inline in the table and only spills to the heap once a second
+// value arrives. A `Many` group always holds at least two values.
+enum Group<V> {
+    One(V),
+    Many(Vec<V>),
+}
+
+impl<V> Group<V> {
+    #[inline(always)]
+    fn as_slice(&self) -> &[V] {
+        match self {
+            Group::One(val) => slice::from_ref(val),
+            Group::Many(vals) => vals,
+        }
+    }
+
+    #[inline(always)]
+    fn as_mut_slice(&mut self) -> &mut [V] {
+        match self {
+            Group::One(val) => slice::from_mut(val),
+            Group::Many(vals) => vals,
+        }
+    }
+
+    #[inline(always)]
+    fn push(&mut self, val: V) {
+        match self {
+            Group::One(_) => match mem::replace(self, Group::Many(Vec::new())) {
+                Group::One(first) => *self = Group::Many(vec![first, val]),
+                Group::Many(_) => unreachable!(),
+            },
+            Group::Many(vals) => vals.push(val),
+        }
+    }
+
+    // Remove the value at `idx`, returning it. The caller must ensure that a `One` group is never emptied.
+    fn remove(&mut self, idx: usize) -> V {
+        let vals = match self {
+            Group::One(_) => unreachable!(),
+            Group::Many(vals) => vals,
+        };
+
+        let val = vals.remove(idx);
+        if vals.len() == 1 {
+            *self = Group::One(vals.pop().unwrap());
+        }
+        val
+    }
+
+    fn into_vec(self) -> Vec<V> {
+        match self {
+            Group::One(val) => vec![val],
+            Group::Many(vals) => vals,
+        }
+    }
+}
+
+/// A map that associates each key with one or more values, in insertion order.
+pub struct MultiMap<K: Hash + Eq, V, S: BuildHasher + Default = FxBuildHasher> {
+    map: HashMap<K, Group<V>, S>,
+    len_values: usize,
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Default> MultiMap<K, V, S> {
+    pub fn new() -> Self {
+        Self {
+            map: HashMap::new(),
+            len_values: 0,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, Default::default())
+    }
+
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self {
+            map: HashMap::with_capacity_and_hasher(capacity, hasher),
+            len_values: 0,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        self.map.hasher()
+    }
+
+    /// The number of distinct keys.
+    pub fn len_keys(&self) -> usize {
+        self.map.len()
+    }
+
+    /// The total number of values across all keys.
+    pub fn len_values(&self) -> usize {
+        self.len_values
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.len_values = 0;
+    }
+
+    /// Append a value to those already associated with `key`.
+    pub fn insert(&mut self, key: K, val: V) {
+        // Grow first, so that the probe below finds the slot that a new key will be placed in
+        self.map.try_grow();
+
+        let hash = HashMap::<K, Group<V>, S>::hash_of(&key, &self.map.hasher);
+        match self.map.probe_idx(hash, |k| k.eq(&key)) {
+            Ok(idx) => unsafe { self.map.val_mut(idx) }.push(val),
+            Err(hint) => { self.map.insert_at(hint, hash, key, Group::One(val)); },
+        }
+        self.len_values += 1;
+    }
+
+    /// The first value associated with `key`.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.get_all(key).first()
+    }
+
+    /// Every value associated with `key`, in insertion order. Empty if the key is not present.
+    pub fn get_all(&self, key: &K) -> &[V] {
+        self.map.get(key).map(Group::as_slice).unwrap_or(&[])
+    }
+
+    pub fn get_all_mut(&mut self, key: &K) -> &mut [V] {
+        self.map.get_mut(key).map(Group::as_mut_slice).unwrap_or(&mut [])
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Remove the first value associated with `key` that is equal to `val`.
+    pub fn remove_one(&mut self, key: &K, val: &V) -> Option<V> where V: PartialEq {
+        let idx = self.map.get_idx(key)?;
+        let group = unsafe { self.map.val_mut(idx) };
+        let pos = group.as_slice().iter().position(|v| v == val)?;
+
+        self.len_values -= 1;
+        match group {
+            Group::One(_) => match self.map.remove_idx(idx).1 {
+                Group::One(val) => Some(val),
+                Group::Many(_) => unreachable!(),
+            },
+            Group::Many(_) => Some(group.remove(pos)),
+        }
+    }
+
+    /// Remove `key` and every value associated with it.
+    pub fn remove_all(&mut self, key: &K) -> Vec<V> {
+        match self.map.remove(key) {
+            Some(group) => {
+                let vals = group.into_vec();
+                self.len_values -= vals.len();
+                vals
+            },
+            None => Vec::new(),
+        }
+    }
+
+    /// Keep only the values for which `f` returns `true`. Keys left without values are removed.
+    pub fn retain_values<F: FnMut(&K, &V) -> bool>(&mut self, mut f: F) {
+        let len_values = &mut self.len_values;
+        self.map.retain(|key, group| {
+            match group {
+                Group::One(val) => if !f(key, val) {
+                    *len_values -= 1;
+                    return false;
+                },
+                Group::Many(vals) => {
+                    let old_len = vals.len();
+                    vals.retain(|val| f(key, val));
+                    *len_values -= old_len - vals.len();
+
+                    match vals.len() {
+                        0 => return false,
+                        1 => *group = Group::One(vals.pop().unwrap()),
+                        _ => {},
+                    }
+                },
+            }
+            true
+        });
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { inner: self.map.keys() }
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter { inner: self.map.iter() }
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Default> Default for MultiMap<K, V, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Keys<'a, K: 'a, V: 'a> {
+    inner: crate::Keys<'a, K, Group<V>>,
+}
+
+impl<'a, K: 'a, V: 'a> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+pub struct Iter<'a, K: 'a, V: 'a> {
+    inner: crate::Iter<'a, K, Group<V>>,
+}
+
+impl<'a, K: 'a, V: 'a> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a [V]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, group)| (k, group.as_slice()))
+    }
+}
@@ -0,0 +1,663 @@
+//! Probing strategies for `HashMap`.
+//!
+//! A strategy decides where keys are placed within a table and how they are found again. Every strategy works over the
+//! same slot storage, so they can be swapped with the `P` type parameter of `HashMap` to compare them on real workloads.
+
+use core::ptr;
+
+use crate::robin_hood;
+
+/// The slots of a table, as seen by a `ProbeStrategy`. The table's capacity is always a power of two.
+pub trait Slots {
+    type Key;
+    type Val;
+
+    fn cap(&self) -> usize;
+
+    fn key(&self, idx: usize) -> Option<&Self::Key>;
+
+    /// Move the entry out of an occupied slot, leaving it empty.
+    ///
+    /// # Safety
+    ///
+    /// The slot at `idx` must be occupied.
+    unsafe fn take(&mut self, idx: usize) -> (Self::Key, Self::Val);
+
+    /// Write an entry into an empty slot.
+    ///
+    /// # Safety
+    ///
+    /// The slot at `idx` must be empty.
+    unsafe fn put(&mut self, idx: usize, key: Self::Key, val: Self::Val);
+
+    /// Exchange an entry with the contents of an occupied slot.
+    ///
+    /// # Safety
+    ///
+    /// The slot at `idx` must be occupied.
+    unsafe fn swap(&mut self, idx: usize, key: &mut Self::Key, val: &mut Self::Val);
+
+    /// Move the entry in the occupied slot `from` to the empty slot `to`.
+    ///
+    /// # Safety
+    ///
+    /// The slot at `from` must be occupied and the slot at `to` must be empty.
+    unsafe fn shift(&mut self, from: usize, to: usize);
+}
+
+/// A scheme for placing keys within a table and finding them again.
+///
+/// The table only stores keys and values, so a strategy keeps whatever else it needs (tombstones, neighbourhood maps,
+/// control bytes) in its `State`, which is rebuilt from scratch whenever the table is resized.
+pub trait ProbeStrategy {
+    type State: Clone;
+
+    /// The state of a table with no slots, for use in a const context.
+    const EMPTY_STATE: Self::State;
+
+    fn new_state(cap: usize) -> Self::State;
+
+    /// The number of slots that may be in use, including any tombstones, before the table must be grown or rebuilt.
+    fn max_used(cap: usize) -> usize {
+        cap
+    }
+
+    /// The number of slots in use, including any tombstones.
+    fn used(_state: &Self::State, len: usize) -> usize {
+        len
+    }
+
+    /// Find either the index of the key that has the given hash and satisfies `eq` or, if there is no such key, a hint
+    /// to pass to `insert`. `hash_of` gives the hash of any stored key.
+    fn probe<T: Slots>(
+        state: &Self::State,
+        slots: &T,
+        hash: u64,
+        eq: impl FnMut(&T::Key) -> bool,
+        hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> Result<usize, usize>;
+
+    /// Insert a key that is known to be absent, using the hint returned by `probe`. Returns the index that the key was
+    /// placed at and whether any existing entries were moved, or hands the entry back if there is no room for it.
+    fn insert<T: Slots>(
+        state: &mut Self::State,
+        slots: &mut T,
+        hint: usize,
+        hash: u64,
+        key: T::Key,
+        val: T::Val,
+        hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> Result<(usize, bool), (T::Key, T::Val)>;
+
+    /// Remove the entry in an occupied slot. Other entries may be moved.
+    fn remove<T: Slots>(
+        state: &mut Self::State,
+        slots: &mut T,
+        idx: usize,
+        hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> (T::Key, T::Val);
+}
+
+// Returned as an insertion hint when a table has no free slot for a key
+const NO_ROOM: usize = usize::MAX;
+
+// Leave a little room so that probe sequences stay short
+#[inline(always)]
+fn seven_eighths(cap: usize) -> usize {
+    cap - cap / 8
+}
+
+/// Robin Hood hashing with backward-shift deletion. Keys that have probed further may displace those that have not,
+/// which keeps probe sequences short and even, and no tombstones are needed. This is the default.
+pub struct RobinHood;
+
+impl ProbeStrategy for RobinHood {
+    type State = ();
+
+    const EMPTY_STATE: () = ();
+
+    fn new_state(_cap: usize) {}
+
+    #[inline(always)]
+    fn probe<T: Slots>(
+        _state: &(),
+        slots: &T,
+        hash: u64,
+        eq: impl FnMut(&T::Key) -> bool,
+        hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> Result<usize, usize> {
+        robin_hood::probe(slots, hash, eq, hash_of)
+    }
+
+    #[inline(always)]
+    fn insert<T: Slots>(
+        _state: &mut (),
+        slots: &mut T,
+        hint: usize,
+        _hash: u64,
+        key: T::Key,
+        val: T::Val,
+        hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> Result<(usize, bool), (T::Key, T::Val)> {
+        // The new key always takes the slot that was found for it
+        Ok((hint, robin_hood::insert_at(slots, hint, key, val, hash_of)))
+    }
+
+    #[inline(always)]
+    fn remove<T: Slots>(
+        _state: &mut (),
+        slots: &mut T,
+        idx: usize,
+        hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> (T::Key, T::Val) {
+        robin_hood::remove_at(slots, idx, hash_of)
+    }
+}
+
+/// The slots left behind by removed keys, which probe sequences must continue past
+#[derive(Clone)]
+pub struct Tombstones {
+    dead: Vec<bool>,
+    count: usize,
+}
+
+impl Tombstones {
+    const EMPTY: Self = Self { dead: Vec::new(), count: 0 };
+
+    fn new(cap: usize) -> Self {
+        Self { dead: vec![false; cap], count: 0 }
+    }
+
+    // Probe the slots at the given offsets from the key's intended index
+    #[inline(always)]
+    fn probe<T: Slots>(
+        &self,
+        slots: &T,
+        hash: u64,
+        mut eq: impl FnMut(&T::Key) -> bool,
+        offset: impl Fn(usize) -> usize,
+    ) -> Result<usize, usize> {
+        let cap = slots.cap();
+        let intended_idx = hash as usize & cap.wrapping_sub(1);
+        let mut free = None;
+        for i in 0..cap {
+            let idx = (intended_idx + offset(i)) & cap.wrapping_sub(1);
+            match slots.key(idx) {
+                Some(k) if eq(k) => return Ok(idx),
+                Some(_) => {},
+                None if self.dead[idx] => { free.get_or_insert(idx); },
+                None => return Err(free.unwrap_or(idx)),
+            }
+        }
+        Err(free.unwrap_or(NO_ROOM))
+    }
+
+    #[inline(always)]
+    fn insert<T: Slots>(&mut self, slots: &mut T, hint: usize, key: T::Key, val: T::Val) -> Result<(usize, bool), (T::Key, T::Val)> {
+        if hint == NO_ROOM {
+            return Err((key, val));
+        }
+
+        if self.dead[hint] {
+            self.dead[hint] = false;
+            self.count -= 1;
+        }
+        unsafe { slots.put(hint, key, val) };
+        Ok((hint, false))
+    }
+
+    #[inline(always)]
+    fn remove<T: Slots>(&mut self, slots: &mut T, idx: usize) -> (T::Key, T::Val) {
+        self.dead[idx] = true;
+        self.count += 1;
+        unsafe { slots.take(idx) }
+    }
+}
+
+/// Linear probing, leaving tombstones behind removed keys.
+pub struct Linear;
+
+impl ProbeStrategy for Linear {
+    type State = Tombstones;
+
+    const EMPTY_STATE: Tombstones = Tombstones::EMPTY;
+
+    fn new_state(cap: usize) -> Tombstones {
+        Tombstones::new(cap)
+    }
+
+    fn max_used(cap: usize) -> usize {
+        seven_eighths(cap)
+    }
+
+    fn used(state: &Tombstones, len: usize) -> usize {
+        len + state.count
+    }
+
+    #[inline(always)]
+    fn probe<T: Slots>(
+        state: &Tombstones,
+        slots: &T,
+        hash: u64,
+        eq: impl FnMut(&T::Key) -> bool,
+        _hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> Result<usize, usize> {
+        state.probe(slots, hash, eq, |i| i)
+    }
+
+    #[inline(always)]
+    fn insert<T: Slots>(
+        state: &mut Tombstones,
+        slots: &mut T,
+        hint: usize,
+        _hash: u64,
+        key: T::Key,
+        val: T::Val,
+        _hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> Result<(usize, bool), (T::Key, T::Val)> {
+        state.insert(slots, hint, key, val)
+    }
+
+    #[inline(always)]
+    fn remove<T: Slots>(
+        state: &mut Tombstones,
+        slots: &mut T,
+        idx: usize,
+        _hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> (T::Key, T::Val) {
+        state.remove(slots, idx)
+    }
+}
+
+/// Quadratic probing, leaving tombstones behind removed keys. Probe offsets are the triangular numbers, which visit
+/// every slot of a table whose capacity is a power of two.
+pub struct Quadratic;
+
+impl ProbeStrategy for Quadratic {
+    type State = Tombstones;
+
+    const EMPTY_STATE: Tombstones = Tombstones::EMPTY;
+
+    fn new_state(cap: usize) -> Tombstones {
+        Tombstones::new(cap)
+    }
+
+    fn max_used(cap: usize) -> usize {
+        seven_eighths(cap)
+    }
+
+    fn used(state: &Tombstones, len: usize) -> usize {
+        len + state.count
+    }
+
+    #[inline(always)]
+    fn probe<T: Slots>(
+        state: &Tombstones,
+        slots: &T,
+        hash: u64,
+        eq: impl FnMut(&T::Key) -> bool,
+        _hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> Result<usize, usize> {
+        state.probe(slots, hash, eq, |i| i * (i + 1) / 2)
+    }
+
+    #[inline(always)]
+    fn insert<T: Slots>(
+        state: &mut Tombstones,
+        slots: &mut T,
+        hint: usize,
+        _hash: u64,
+        key: T::Key,
+        val: T::Val,
+        _hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> Result<(usize, bool), (T::Key, T::Val)> {
+        state.insert(slots, hint, key, val)
+    }
+
+    #[inline(always)]
+    fn remove<T: Slots>(
+        state: &mut Tombstones,
+        slots: &mut T,
+        idx: usize,
+        _hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> (T::Key, T::Val) {
+        state.remove(slots, idx)
+    }
+}
+
+// The size of a hopscotch neighbourhood
+const HOP_RANGE: usize = 32;
+
+/// Hopscotch hashing. Every key is kept within a small neighbourhood of its intended index, and each index records
+/// which slots of its neighbourhood hold its keys, so a lookup checks only those slots.
+///
+/// If no slot can be freed within a key's neighbourhood then the table grows, unless it is at most half full: then the
+/// key goes in the nearest free slot and on an overflow list that every lookup also checks. Growing cannot separate
+/// keys that share a hash, so more than 32 of them slow lookups down rather than growing the table without end.
+pub struct Hopscotch;
+
+/// The neighbourhoods of a hopscotch table
+#[derive(Clone)]
+pub struct Hops {
+    hops: Vec<u32>, // For each index, a bitmap of the slots in its neighbourhood that hold its keys
+    overflow: Vec<usize>, // The slots of keys that were placed outside their neighbourhood
+    len: usize,
+}
+
+impl Hops {
+    const EMPTY: Self = Self { hops: Vec::new(), overflow: Vec::new(), len: 0 };
+}
+
+impl ProbeStrategy for Hopscotch {
+    type State = Hops;
+
+    const EMPTY_STATE: Hops = Hops::EMPTY;
+
+    fn new_state(cap: usize) -> Hops {
+        Hops { hops: vec![0; cap], overflow: Vec::new(), len: 0 }
+    }
+
+    #[inline(always)]
+    fn probe<T: Slots>(
+        state: &Hops,
+        slots: &T,
+        hash: u64,
+        mut eq: impl FnMut(&T::Key) -> bool,
+        _hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> Result<usize, usize> {
+        let cap = slots.cap();
+        let intended_idx = hash as usize & cap.wrapping_sub(1);
+        if cap == 0 {
+            return Err(NO_ROOM);
+        }
+
+        let mut hop = state.hops[intended_idx];
+        while hop != 0 {
+            let idx = (intended_idx + hop.trailing_zeros() as usize) & cap.wrapping_sub(1);
+            if slots.key(idx).is_some_and(&mut eq) {
+                return Ok(idx);
+            }
+            hop &= hop - 1;
+        }
+        for &idx in &state.overflow {
+            if slots.key(idx).is_some_and(&mut eq) {
+                return Ok(idx);
+            }
+        }
+        Err(intended_idx)
+    }
+
+    #[inline(always)]
+    fn insert<T: Slots>(
+        state: &mut Hops,
+        slots: &mut T,
+        hint: usize,
+        _hash: u64,
+        key: T::Key,
+        val: T::Val,
+        _hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> Result<(usize, bool), (T::Key, T::Val)> {
+        if hint == NO_ROOM {
+            return Err((key, val));
+        }
+
+        let cap = slots.cap();
+        let mask = cap.wrapping_sub(1);
+        let dist = |from: usize, to: usize| (cap + to - from) & mask;
+        let hops = &mut state.hops;
+
+        // Find the nearest free slot
+        let mut free = match (0..cap).map(|i| (hint + i) & mask).find(|idx| slots.key(*idx).is_none()) {
+            Some(free) => free,
+            None => return Err((key, val)),
+        };
+
+        // Move it back towards the intended index by swapping it with keys that may move forward
+        let mut moved = false;
+        while dist(hint, free) >= HOP_RANGE {
+            let mut hopped = false;
+            for back in (1..HOP_RANGE).rev() {
+                let home = (cap + free - back) & mask;
+                // The first of this index's keys that lies before the free slot
+                let hop = hops[home] & ((1 << back) - 1);
+                if hop != 0 {
+                    let offset = hop.trailing_zeros() as usize;
+                    let from = (home + offset) & mask;
+                    unsafe { slots.shift(from, free) };
+                    hops[home] &= !(1 << offset);
+                    hops[home] |= 1 << back;
+                    free = from;
+                    moved = true;
+                    hopped = true;
+                    break;
+                }
+            }
+
+            if !hopped {
+                // In a table that is at most half full, the neighbourhood is crowded by keys that share this key's
+                // hash, which growing cannot separate
+                if 2 * (state.len + 1) > cap {
+                    return Err((key, val));
+                }
+                unsafe { slots.put(free, key, val) };
+                state.overflow.push(free);
+                state.len += 1;
+                return Ok((free, moved));
+            }
+        }
+
+        unsafe { slots.put(free, key, val) };
+        hops[hint] |= 1 << dist(hint, free);
+        state.len += 1;
+        Ok((free, moved))
+    }
+
+    #[inline(always)]
+    fn remove<T: Slots>(
+        state: &mut Hops,
+        slots: &mut T,
+        idx: usize,
+        mut hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> (T::Key, T::Val) {
+        let cap = slots.cap();
+        let intended_idx = hash_of(slots.key(idx).unwrap()) as usize & cap.wrapping_sub(1);
+        // Keys are only placed outside their neighbourhood when they overflow
+        let dist = (cap + idx - intended_idx) & cap.wrapping_sub(1);
+        if dist < HOP_RANGE {
+            state.hops[intended_idx] &= !(1 << dist);
+        } else {
+            let pos = state.overflow.iter().position(|&o| o == idx).unwrap();
+            state.overflow.swap_remove(pos);
+        }
+        state.len -= 1;
+        unsafe { slots.take(idx) }
+    }
+}
+
+// The number of control bytes that are scanned together
+const GROUP_LEN: usize = 8;
+
+const CTRL_EMPTY: u8 = 0xFF;
+const CTRL_DELETED: u8 = 0x80; // Full slots hold the top 7 bits of their key's hash, so never have the top bit set
+
+#[inline(always)]
+fn repeat(byte: u8) -> u64 {
+    u64::from_ne_bytes([byte; GROUP_LEN])
+}
+
+// A set of the bytes within a group, one bit per byte
+#[derive(Copy, Clone)]
+struct BitMask(u64);
+
+impl Iterator for BitMask {
+    type Item = usize;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            None
+        } else {
+            let byte = self.0.trailing_zeros() as usize / 8;
+            self.0 &= self.0 - 1;
+            Some(byte)
+        }
+    }
+}
+
+/// The control bytes of a Swiss table
+#[derive(Clone)]
+pub struct Control {
+    ctrl: Vec<u8>,
+    deleted: usize,
+}
+
+impl Control {
+    #[inline(always)]
+    fn h2(hash: u64) -> u8 {
+        (hash >> 57) as u8
+    }
+
+    // The control bytes of a group and a mask of the bytes that belong to the table, for tables smaller than a group
+    #[inline(always)]
+    fn group(&self, group: usize) -> (u64, u64) {
+        if self.ctrl.len() >= GROUP_LEN {
+            let start = group * GROUP_LEN;
+            debug_assert!(start + GROUP_LEN <= self.ctrl.len());
+            let bytes = unsafe { ptr::read_unaligned(self.ctrl.as_ptr().add(start) as *const u64) };
+            (bytes, repeat(0x80))
+        } else {
+            let len = self.ctrl.len();
+            let mut bytes = [CTRL_EMPTY; GROUP_LEN];
+            bytes[..len].copy_from_slice(&self.ctrl);
+            (u64::from_ne_bytes(bytes), repeat(0x80) & ((1 << (len * 8)) - 1))
+        }
+    }
+
+    // Bytes equal to `byte`. May include false positives, which is fine when they are checked afterwards.
+    #[inline(always)]
+    fn match_byte(group: u64, byte: u8) -> u64 {
+        let cmp = group ^ repeat(byte);
+        cmp.wrapping_sub(repeat(0x01)) & !cmp & repeat(0x80)
+    }
+
+    // Only `CTRL_EMPTY` has both of its top two bits set
+    #[inline(always)]
+    fn match_empty(group: u64) -> u64 {
+        group & (group << 1) & repeat(0x80)
+    }
+
+    #[inline(always)]
+    fn match_empty_or_deleted(group: u64) -> u64 {
+        group & repeat(0x80)
+    }
+
+    // Convert a mask of byte positions from native byte order into an iterator over byte indices
+    #[inline(always)]
+    fn bytes(mask: u64) -> BitMask {
+        BitMask(if cfg!(target_endian = "little") { mask } else { mask.swap_bytes() })
+    }
+}
+
+/// A Swiss table. Each slot has a control byte that holds either 7 bits of its key's hash or a marker for an empty or
+/// deleted slot. Control bytes are scanned a group at a time, so most keys that do not match are rejected without
+/// being compared.
+pub struct Swiss;
+
+impl ProbeStrategy for Swiss {
+    type State = Control;
+
+    const EMPTY_STATE: Control = Control { ctrl: Vec::new(), deleted: 0 };
+
+    fn new_state(cap: usize) -> Control {
+        Control { ctrl: vec![CTRL_EMPTY; cap], deleted: 0 }
+    }
+
+    fn max_used(cap: usize) -> usize {
+        seven_eighths(cap)
+    }
+
+    fn used(state: &Control, len: usize) -> usize {
+        len + state.deleted
+    }
+
+    #[inline(always)]
+    fn probe<T: Slots>(
+        state: &Control,
+        slots: &T,
+        hash: u64,
+        mut eq: impl FnMut(&T::Key) -> bool,
+        _hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> Result<usize, usize> {
+        let cap = slots.cap();
+        let group_count = (cap / GROUP_LEN).max(1);
+        let h2 = Control::h2(hash);
+
+        // Groups are probed quadratically
+        let first_group = (hash as usize & cap.wrapping_sub(1)) / GROUP_LEN;
+        let mut free = None;
+        for i in 0..group_count {
+            let group = (first_group + i * (i + 1) / 2) & (group_count - 1);
+            let (ctrl, valid) = state.group(group);
+
+            for byte in Control::bytes(Control::match_byte(ctrl, h2) & valid) {
+                let idx = group * GROUP_LEN + byte;
+                if slots.key(idx).is_some_and(&mut eq) {
+                    return Ok(idx);
+                }
+            }
+
+            if free.is_none() {
+                free = Control::bytes(Control::match_empty_or_deleted(ctrl) & valid)
+                    .next()
+                    .map(|byte| group * GROUP_LEN + byte);
+            }
+
+            // Keys are never placed beyond a group that had an empty slot
+            if Control::match_empty(ctrl) & valid != 0 {
+                break;
+            }
+        }
+        Err(free.unwrap_or(NO_ROOM))
+    }
+
+    #[inline(always)]
+    fn insert<T: Slots>(
+        state: &mut Control,
+        slots: &mut T,
+        hint: usize,
+        hash: u64,
+        key: T::Key,
+        val: T::Val,
+        _hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> Result<(usize, bool), (T::Key, T::Val)> {
+        if hint == NO_ROOM {
+            return Err((key, val));
+        }
+
+        if state.ctrl[hint] == CTRL_DELETED {
+            state.deleted -= 1;
+        }
+        state.ctrl[hint] = Control::h2(hash);
+        unsafe { slots.put(hint, key, val) };
+        Ok((hint, false))
+    }
+
+    #[inline(always)]
+    fn remove<T: Slots>(
+        state: &mut Control,
+        slots: &mut T,
+        idx: usize,
+        _hash_of: impl FnMut(&T::Key) -> u64,
+    ) -> (T::Key, T::Val) {
+        // If the group already has an empty slot then no probe sequence continues past it, so this slot can be emptied
+        // too. Otherwise, it must be marked as deleted.
+        let (ctrl, valid) = state.group(idx / GROUP_LEN);
+        if Control::match_empty(ctrl) & valid != 0 {
+            state.ctrl[idx] = CTRL_EMPTY;
+        } else {
+            state.ctrl[idx] = CTRL_DELETED;
+            state.deleted += 1;
+        }
+        unsafe { slots.take(idx) }
+    }
+}
@@ -0,0 +1,510 @@
+//! The table that `HashMap` is built on, for building other containers.
+//!
+//! A `RawTable` stores keys and their values and knows how to place, find and move them, but never hashes or compares
+//! keys itself: every operation that looks for a key is given its hash and an `eq` closure by the caller. Each key's
+//! hash is stored alongside it, so the table can move entries about without calling back into the caller, and `eq` is
+//! only called for keys whose hash matches.
+
+use std::{
+    ptr,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{
+    layout::{Layout, SoA, Storage, TrivialClone},
+    probe::{ProbeStrategy, RobinHood, Slots},
+};
+
+// Tables are numbered so that a bucket from one is never mistaken for a bucket from another. Zero is left for tables
+// with no slots, which have no buckets to give out.
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+#[inline(always)]
+fn next_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+// A key as it is kept in a table, along with its hash
+#[derive(Copy, Clone)]
+pub(crate) struct StoredKey<K> {
+    pub(crate) hash: u64,
+    pub(crate) key: K,
+}
+
+impl<K: TrivialClone> TrivialClone for StoredKey<K> {}
+
+#[inline(always)]
+fn stored_hash<K>(key: &StoredKey<K>) -> u64 {
+    key.hash
+}
+
+/// The position of an entry in a `RawTable`, as returned by `find`, `insert` and `iter`.
+///
+/// Like a `Handle`, a bucket is invalidated when its entry moves or is removed, but not when other entries do. Invalid
+/// buckets are always detected, as are buckets from another table (including a clone of this one).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Bucket {
+    idx: usize,
+    table: u64,
+    generation: u64,
+}
+
+/// A hash table of keys and values, generic over the `ProbeStrategy` used to place its keys and the `Layout` of its
+/// slots in memory. For a table of plain values, leave `V` as `()`.
+///
+/// The table only grows when an insertion needs room, and never shrinks unless asked to with `shrink_to`.
+pub struct RawTable<K, V = (), P: ProbeStrategy = RobinHood, L: Layout = SoA> {
+    pub(crate) slots: L::Storage<StoredKey<K>, V>,
+    state: P::State,
+    generations: Vec<u64>, // For each slot, bumped whenever an entry is moved into it, invalidating its buckets
+
+    len: usize, // Always <= cap
+    pub(crate) cap: usize, // Always 2^n
+    id: u64, // Changed whenever every entry moves at once, invalidating all buckets
+}
+
+impl<K, V, P: ProbeStrategy, L: Layout> RawTable<K, V, P, L> {
+    // Private interface
+
+    // The smallest capacity that can hold `len` entries
+    #[inline(always)]
+    fn cap_for(len: usize) -> usize {
+        let cap = len.next_power_of_two();
+        if P::max_used(cap) < len {
+            2 * cap
+        } else {
+            cap
+        }
+    }
+
+    pub(crate) fn resize_to(&mut self, new_cap: usize) {
+        assert!(new_cap.is_power_of_two());
+        assert!(P::max_used(new_cap) >= self.len);
+
+        let mut new_slots = L::Storage::with_capacity(new_cap);
+        let mut new_state = P::new_state(new_cap);
+
+        // For each value in the existing table
+        let mut overflow = Vec::new();
+        let mut idx = 0;
+        while let Some(occupied) = self.slots.next_occupied(idx) {
+            let (key, val) = unsafe { self.slots.take(occupied) };
+            let hint = P::probe(&new_state, &new_slots, key.hash, |_| false, stored_hash).unwrap_err();
+            if let Err(entry) = P::insert(&mut new_state, &mut new_slots, hint, key.hash, key, val, stored_hash) {
+                overflow.push(entry);
+            }
+            idx = occupied + 1;
+        }
+
+        self.slots = new_slots;
+        self.state = new_state;
+        self.generations = vec![0; new_cap];
+        self.cap = new_cap;
+        self.id = next_id();
+
+        // Some strategies may find no room for a key even at this capacity, in which case the table must grow further
+        for (key, val) in overflow {
+            let hint = self.probe(key.hash, |_| false).unwrap_err();
+            self.len -= 1;
+            self.insert_at(hint, key.hash, key.key, val);
+        }
+    }
+
+    // Make room for one more key, returning whether the slots were moved, and so whether earlier hints are stale
+    #[inline(always)]
+    pub(crate) fn try_grow(&mut self) -> bool {
+        // Only grow if every usable slot is taken
+        if P::used(&self.state, self.len) < P::max_used(self.cap) {
+            return false;
+        }
+
+        if self.cap == 0 {
+            self.resize_to(1);
+            return true;
+        }
+
+        // If most of the used slots are tombstones then clearing them out is enough
+        if self.len < P::max_used(self.cap) / 2 {
+            self.resize_to(self.cap);
+        } else {
+            self.resize_to(2 * self.cap);
+        }
+        true
+    }
+
+    #[inline(always)]
+    pub(crate) fn try_shrink(&mut self) {
+        // Only shrink if len <= quarter of capacity
+        if self.cap <= 1 || self.len > self.cap / 4 {
+            return;
+        }
+
+        self.resize_to(self.cap / 2);
+    }
+
+    // Find either the index of the key that has the given hash and satisfies `eq` or, if there is no such key, a hint
+    // for `insert_at`
+    #[inline(always)]
+    pub(crate) fn probe(&self, hash: u64, mut eq: impl FnMut(&K) -> bool) -> Result<usize, usize> {
+        P::probe(&self.state, &self.slots, hash, |k| k.hash == hash && eq(&k.key), stored_hash)
+    }
+
+    // Insert a key that is known to be absent, using the hint returned by `probe`, growing the table if the strategy
+    // finds no room for it. Returns the index of the new entry.
+    pub(crate) fn insert_at(&mut self, mut hint: usize, hash: u64, key: K, val: V) -> usize {
+        let mut entry = (StoredKey { hash, key }, val);
+        // Every strategy finds room for a key in a table that is at most half full, so this grows the table at most
+        // twice
+        loop {
+            let mut slots = Tracked::new(&mut self.slots, &mut self.generations, NO_WATCH);
+            match P::insert(&mut self.state, &mut slots, hint, hash, entry.0, entry.1, stored_hash) {
+                Ok((idx, _)) => {
+                    self.len += 1;
+                    return idx;
+                },
+                Err(returned) => {
+                    entry = returned;
+                    self.resize_to((2 * self.cap).max(1));
+                    hint = self.probe(hash, |_| false).unwrap_err();
+                },
+            }
+        }
+    }
+
+    // Remove the entry in an occupied slot, without shrinking the table
+    #[inline(always)]
+    pub(crate) fn remove_at(&mut self, idx: usize) -> (K, V) {
+        self.remove_at_watching(idx, NO_WATCH).0
+    }
+
+    // As `remove_at`, but also report whether the entry in the `watch` slot was shifted to make up for the removal
+    pub(crate) fn remove_at_watching(&mut self, idx: usize, watch: usize) -> ((K, V), bool) {
+        let mut slots = Tracked::new(&mut self.slots, &mut self.generations, watch);
+        let (key, val) = P::remove(&mut self.state, &mut slots, idx, stored_hash);
+        let moved = slots.moved;
+
+        self.len -= 1;
+
+        ((key.key, val), moved)
+    }
+
+    // Move every entry out, leaving the table empty but keeping its capacity
+    pub(crate) fn take_entries(&mut self) -> Vec<(K, V)> {
+        let mut entries = Vec::with_capacity(self.len);
+        let mut idx = 0;
+        while let Some(occupied) = self.slots.next_occupied(idx) {
+            let (key, val) = unsafe { self.slots.take(occupied) };
+            entries.push((key.key, val));
+            idx = occupied + 1;
+        }
+        self.state = P::new_state(self.cap);
+        self.len = 0;
+        entries
+    }
+
+    #[inline(always)]
+    pub(crate) fn bucket(&self, idx: usize) -> Bucket {
+        Bucket { idx, table: self.id, generation: self.generations[idx] }
+    }
+
+    // The slot that a bucket refers to, if its entry is still there. A slot's generation changes whenever an entry
+    // moves into it, but not when its entry is removed, so the slot must also be checked for an entry.
+    #[inline(always)]
+    pub(crate) fn bucket_idx(&self, bucket: Bucket) -> Option<usize> {
+        if bucket.table == self.id
+            && bucket.idx < self.cap
+            && self.generations[bucket.idx] == bucket.generation
+            && self.slots.key(bucket.idx).is_some()
+        {
+            Some(bucket.idx)
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn next_occupied(&self, from: usize) -> Option<usize> {
+        self.slots.next_occupied(from)
+    }
+
+    #[inline(always)]
+    pub(crate) fn prefetch(&self, hash: u64) {
+        if self.cap > 0 {
+            self.slots.prefetch(hash as usize & (self.cap - 1));
+        }
+    }
+
+    // The key in slot `idx`, if it is occupied
+    #[inline(always)]
+    pub(crate) fn key(&self, idx: usize) -> Option<&K> {
+        self.slots.key(idx).map(|k| &k.key)
+    }
+
+    // The following require that `idx` is occupied, and give the same access to its entry as `Storage`
+
+    #[inline(always)]
+    pub(crate) unsafe fn key_ref(&self, idx: usize) -> &K {
+        &self.slots.key_ref(idx).key
+    }
+
+    #[inline(always)]
+    pub(crate) unsafe fn val_ref(&self, idx: usize) -> &V {
+        self.slots.val_ref(idx)
+    }
+
+    #[inline(always)]
+    pub(crate) unsafe fn key_ptr(&self, idx: usize) -> *mut K {
+        ptr::addr_of_mut!((*self.slots.key_ptr(idx)).key)
+    }
+
+    #[inline(always)]
+    pub(crate) unsafe fn val_ptr(&self, idx: usize) -> *mut V {
+        self.slots.val_ptr(idx)
+    }
+
+    // Public interface
+
+    pub const fn new() -> Self {
+        Self {
+            slots: <L::Storage<StoredKey<K>, V> as Storage>::EMPTY,
+            state: P::EMPTY_STATE,
+            generations: Vec::new(),
+
+            len: 0,
+            cap: 0,
+            id: 0,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let cap = Self::cap_for(capacity);
+        Self {
+            slots: L::Storage::with_capacity(cap),
+            state: P::new_state(cap),
+            generations: vec![0; cap],
+
+            len: 0,
+            cap,
+            id: next_id(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        P::max_used(self.cap)
+    }
+
+    /// Make room for at least `additional` more entries.
+    pub fn reserve(&mut self, additional: usize) {
+        if additional > (self.capacity() - self.len) {
+            self.resize_to(Self::cap_for(self.len + additional));
+        }
+    }
+
+    /// Shrink the table as far as possible while keeping room for at least `min_capacity` entries.
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.resize_to(Self::cap_for(self.len.max(min_capacity)));
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        let mut idx = 0;
+        while let Some(occupied) = self.slots.next_occupied(idx) {
+            drop(unsafe { self.slots.take(occupied) });
+            idx = occupied + 1;
+        }
+        self.state = P::new_state(self.cap);
+        self.len = 0;
+    }
+
+    /// Find the entry whose key has the given hash and satisfies `eq`.
+    pub fn find(&self, hash: u64, eq: impl FnMut(&K) -> bool) -> Option<Bucket> {
+        self.probe(hash, eq).ok().map(|idx| self.bucket(idx))
+    }
+
+    /// Insert an entry without checking whether an equal key is already present.
+    pub fn insert(&mut self, hash: u64, key: K, val: V) -> Bucket {
+        self.try_grow();
+        // Equal keys are placed after any that are already present
+        let hint = self.probe(hash, |_| false).unwrap_err();
+        let idx = self.insert_at(hint, hash, key, val);
+        self.bucket(idx)
+    }
+
+    /// Remove the entry in a bucket. Returns `None` if the bucket is no longer valid.
+    pub fn erase(&mut self, bucket: Bucket) -> Option<(K, V)> {
+        let idx = self.bucket_idx(bucket)?;
+        Some(self.remove_at(idx))
+    }
+
+    pub fn is_valid(&self, bucket: Bucket) -> bool {
+        self.bucket_idx(bucket).is_some()
+    }
+
+    pub fn get(&self, bucket: Bucket) -> Option<(&K, &V)> {
+        self.bucket_idx(bucket).map(|idx| unsafe { (self.key_ref(idx), self.val_ref(idx)) })
+    }
+
+    /// Get the entry in a bucket. The key may be changed, so long as its hash and equality stay the same.
+    pub fn get_mut(&mut self, bucket: Bucket) -> Option<(&mut K, &mut V)> {
+        match self.bucket_idx(bucket) {
+            Some(idx) => Some(unsafe { (&mut *self.key_ptr(idx), &mut *self.val_ptr(idx)) }),
+            None => None,
+        }
+    }
+
+    /// Remove every entry for which `f` returns `false`, then rebuild the table around the survivors. As with
+    /// `get_mut`, keys may be changed so long as their hash and equality stay the same.
+    pub fn retain(&mut self, mut f: impl FnMut(&mut K, &mut V) -> bool) {
+        let mut removed = false;
+        let mut idx = 0;
+        while let Some(occupied) = self.slots.next_occupied(idx) {
+            if !f(unsafe { &mut *self.key_ptr(occupied) }, unsafe { &mut *self.val_ptr(occupied) }) {
+                drop(unsafe { self.slots.take(occupied) });
+                self.len -= 1;
+                removed = true;
+            }
+            idx = occupied + 1;
+        }
+
+        // Removal leaves holes in probe sequences, so rebuild the table around the survivors
+        if removed {
+            self.resize_to(self.cap);
+            self.try_shrink();
+        }
+    }
+
+    /// Iterate over the buckets of every entry, in slot order.
+    pub fn iter(&self) -> RawIter<'_, K, V, L> {
+        RawIter {
+            slots: &self.slots,
+            generations: &self.generations,
+            idx: 0,
+            table: self.id,
+        }
+    }
+}
+
+// The table uniquely owns its keys and values, just like a `Vec` does
+unsafe impl<K: Send, V: Send, P: ProbeStrategy, L: Layout> Send for RawTable<K, V, P, L> where P::State: Send {}
+unsafe impl<K: Sync, V: Sync, P: ProbeStrategy, L: Layout> Sync for RawTable<K, V, P, L> where P::State: Sync {}
+
+impl<K, V, P: ProbeStrategy, L: Layout> Default for RawTable<K, V, P, L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Clone, V: Clone, P: ProbeStrategy, L: Layout> Clone for RawTable<K, V, P, L> {
+    fn clone(&self) -> Self {
+        // Entries keep their positions, so the strategy's state remains valid for the copy
+        // The copy is a different table, so buckets into this one must not be valid for it
+        Self {
+            slots: self.slots.clone_slots(),
+            state: self.state.clone(),
+            generations: self.generations.clone(),
+
+            len: self.len,
+            cap: self.cap,
+            id: next_id(),
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.slots.clone_slots_from(&source.slots);
+        self.state.clone_from(&source.state);
+        self.generations.clone_from(&source.generations);
+
+        self.len = source.len;
+        self.cap = source.cap;
+        // Buckets into either table must not be valid for the copy
+        self.id = next_id();
+    }
+}
+
+pub struct RawIter<'a, K: 'a, V: 'a, L: Layout = SoA> {
+    slots: &'a L::Storage<StoredKey<K>, V>,
+    generations: &'a [u64],
+    idx: usize,
+    table: u64,
+}
+
+impl<'a, K: 'a, V: 'a, L: Layout> Iterator for RawIter<'a, K, V, L> {
+    type Item = Bucket;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.slots.next_occupied(self.idx)?;
+        self.idx = idx + 1;
+        Some(Bucket { idx, table: self.table, generation: self.generations[idx] })
+    }
+}
+
+// Passed as the `watch` slot of `Tracked` when no slot needs watching
+const NO_WATCH: usize = usize::MAX;
+
+// Forwards to another set of slots, bumping the generation of every slot that an entry moves into and noting whether
+// the entry in the `watch` slot was shifted. Generations are 64 bits so that they never wrap around (bumping one every
+// nanosecond would take centuries), since a wrapped generation would let a stale bucket refer to the wrong entry.
+struct Tracked<'a, T: Slots> {
+    slots: &'a mut T,
+    generations: &'a mut [u64],
+    watch: usize,
+    moved: bool,
+}
+
+impl<'a, T: Slots> Tracked<'a, T> {
+    #[inline(always)]
+    fn new(slots: &'a mut T, generations: &'a mut [u64], watch: usize) -> Self {
+        Self { slots, generations, watch, moved: false }
+    }
+
+    #[inline(always)]
+    fn bump(&mut self, idx: usize) {
+        self.generations[idx] += 1;
+    }
+}
+
+impl<'a, T: Slots> Slots for Tracked<'a, T> {
+    type Key = T::Key;
+    type Val = T::Val;
+
+    #[inline(always)]
+    fn cap(&self) -> usize {
+        self.slots.cap()
+    }
+
+    #[inline(always)]
+    fn key(&self, idx: usize) -> Option<&T::Key> {
+        self.slots.key(idx)
+    }
+
+    #[inline(always)]
+    unsafe fn take(&mut self, idx: usize) -> (T::Key, T::Val) {
+        self.slots.take(idx)
+    }
+
+    #[inline(always)]
+    unsafe fn put(&mut self, idx: usize, key: T::Key, val: T::Val) {
+        self.bump(idx);
+        self.slots.put(idx, key, val)
+    }
+
+    #[inline(always)]
+    unsafe fn swap(&mut self, idx: usize, key: &mut T::Key, val: &mut T::Val) {
+        self.bump(idx);
+        self.slots.swap(idx, key, val)
+    }
+
+    #[inline(always)]
+    unsafe fn shift(&mut self, from: usize, to: usize) {
+        self.moved |= from == self.watch;
+        self.bump(to);
+        self.slots.shift(from, to)
+    }
+}
@@ -0,0 +1,91 @@
+// The Robin Hood probing scheme, shared by every table in the crate that uses it. A table exposes its slots through
+// `Slots` and supplies a `hash_of` function that gives the hash of a stored key.
+
+use crate::probe::Slots;
+
+// Whether a key intended for `intended_idx` has probed further than the occupant intended for `other_intended_idx`
+#[inline(always)]
+fn is_poorer(cap: usize, intended_idx: usize, other_intended_idx: usize) -> bool {
+    (cap + intended_idx - other_intended_idx) & cap.wrapping_sub(1) > cap / 2
+}
+
+// Find either the index of the key that has the given hash and satisfies `eq` or, if there is no such key, the index at
+// which it should be inserted
+#[inline(always)]
+pub(crate) fn probe<T: Slots>(
+    slots: &T,
+    hash: u64,
+    mut eq: impl FnMut(&T::Key) -> bool,
+    mut hash_of: impl FnMut(&T::Key) -> u64,
+) -> Result<usize, usize> {
+    let cap = slots.cap();
+    let intended_idx = hash as usize & cap.wrapping_sub(1);
+    let mut idx = intended_idx;
+    for _ in 0..cap {
+        match slots.key(idx) {
+            None => break,
+            Some(k) if eq(k) => return Ok(idx),
+            Some(k) if is_poorer(cap, intended_idx, hash_of(k) as usize & cap.wrapping_sub(1)) => break,
+            _ => {},
+        }
+
+        idx = (idx + 1) & cap.wrapping_sub(1);
+    }
+    Err(idx)
+}
+
+// Insert a key that is known to be absent, starting at `idx` (as found by `probe`, or the key's intended index) and
+// pushing any occupants further along. There must be a free slot. Returns whether any existing entries were moved.
+#[inline(always)]
+pub(crate) fn insert_at<T: Slots>(
+    slots: &mut T,
+    mut idx: usize,
+    mut key: T::Key,
+    mut val: T::Val,
+    mut hash_of: impl FnMut(&T::Key) -> u64,
+) -> bool {
+    let cap = slots.cap();
+    let mut moved = false;
+
+    let mut intended_idx = hash_of(&key) as usize & cap.wrapping_sub(1);
+    loop {
+        match slots.key(idx) {
+            None => break,
+            Some(k) => { // Robin Hood swapping
+                let other_intended_idx = hash_of(k) as usize & cap.wrapping_sub(1);
+                if is_poorer(cap, intended_idx, other_intended_idx) {
+                    unsafe { slots.swap(idx, &mut key, &mut val) };
+                    intended_idx = other_intended_idx;
+                    moved = true;
+                }
+            },
+        }
+        idx = (idx + 1) & cap.wrapping_sub(1);
+    }
+
+    unsafe { slots.put(idx, key, val) };
+    moved
+}
+
+// Remove the entry in an occupied slot, shifting any displaced successors back into the hole
+#[inline(always)]
+pub(crate) fn remove_at<T: Slots>(
+    slots: &mut T,
+    idx: usize,
+    mut hash_of: impl FnMut(&T::Key) -> u64,
+) -> (T::Key, T::Val) {
+    let cap = slots.cap();
+    let entry = unsafe { slots.take(idx) };
+
+    let mut hole = idx;
+    loop {
+        let next = (hole + 1) & cap.wrapping_sub(1);
+        match slots.key(next) {
+            Some(k) if hash_of(k) as usize & cap.wrapping_sub(1) != next => unsafe { slots.shift(next, hole) },
+            _ => break,
+        }
+        hole = next;
+    }
+
+    entry
+}
@@ -0,0 +1,421 @@
+use std::{
+    hash::{BuildHasher, Hash},
+    mem::{self, MaybeUninit},
+    ptr, slice,
+};
+
+use fxhash::FxBuildHasher;
+use allocator_api::alloc::CollectionAllocErr;
+
+use crate::HashMap;
+
+// The largest inline capacity for which a lookup compares the key with every entry. Beyond it, comparing keys that
+// cannot match costs more than hashing, so each entry also keeps a tag from its key's hash and only entries with the
+// same tag are compared.
+const LINEAR_MAX: usize = 8;
+
+// Up to `N` entries, stored in insertion order
+struct Inline<K, V, S, const N: usize> {
+    entries: [MaybeUninit<(K, V)>; N],
+    tags: [u8; N], // The top bits of each key's hash, only kept if `N > LINEAR_MAX`
+    len: usize,
+    hasher: S,
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher, const N: usize> Inline<K, V, S, N> {
+    fn new(hasher: S) -> Self {
+        Self {
+            // An array of `MaybeUninit` needs no initialisation
+            entries: unsafe { MaybeUninit::uninit().assume_init() },
+            tags: [0; N],
+            len: 0,
+            hasher,
+        }
+    }
+
+    #[inline(always)]
+    fn as_slice(&self) -> &[(K, V)] {
+        unsafe { slice::from_raw_parts(self.entries.as_ptr() as *const (K, V), self.len) }
+    }
+
+    #[inline(always)]
+    fn as_mut_slice(&mut self) -> &mut [(K, V)] {
+        unsafe { slice::from_raw_parts_mut(self.entries.as_mut_ptr() as *mut (K, V), self.len) }
+    }
+
+    // Find either the index of the key or, if it is absent, the tag to push it with
+    #[inline(always)]
+    fn find(&self, key: &K) -> Result<usize, u8> {
+        if N <= LINEAR_MAX {
+            return self.as_slice().iter().position(|(k, _)| k.eq(key)).ok_or(0);
+        }
+
+        let tag = (self.hasher.hash_one(key) >> 57) as u8;
+        let entries = self.as_slice();
+        (0..self.len)
+            .find(|idx| self.tags[*idx] == tag && entries[*idx].0.eq(key))
+            .ok_or(tag)
+    }
+
+    #[inline(always)]
+    fn position(&self, key: &K) -> Option<usize> {
+        self.find(key).ok()
+    }
+
+    // There must be room for another entry
+    #[inline(always)]
+    fn push(&mut self, tag: u8, key: K, val: V) {
+        self.entries[self.len] = MaybeUninit::new((key, val));
+        self.tags[self.len] = tag;
+        self.len += 1;
+    }
+
+    #[inline(always)]
+    fn swap_remove(&mut self, idx: usize) -> (K, V) {
+        self.len -= 1;
+        unsafe {
+            let entry = ptr::read(self.entries[idx].as_ptr());
+            if idx != self.len {
+                ptr::copy_nonoverlapping(self.entries[self.len].as_ptr(), self.entries[idx].as_mut_ptr(), 1);
+                self.tags[idx] = self.tags[self.len];
+            }
+            entry
+        }
+    }
+
+    // Move every entry out, leaving the storage empty
+    fn drain(&mut self) -> impl Iterator<Item = (K, V)> + '_ {
+        let len = mem::replace(&mut self.len, 0);
+        self.entries[..len].iter().map(|entry| unsafe { ptr::read(entry.as_ptr()) })
+    }
+}
+
+impl<K, V, S, const N: usize> Drop for Inline<K, V, S, N> {
+    fn drop(&mut self) {
+        for entry in &mut self.entries[..self.len] {
+            unsafe { ptr::drop_in_place(entry.as_mut_ptr()) };
+        }
+    }
+}
+
+enum Repr<K: Hash + Eq, V, S: BuildHasher + Default, const N: usize> {
+    Inline(Inline<K, V, S, N>),
+    Heap(HashMap<K, V, S>),
+}
+
+/// A `HashMap` that holds up to `N` entries inline, without allocating. Once it grows beyond `N` entries it
+/// transparently moves them to a regular `HashMap`.
+///
+/// While inline, a map with room for at most 8 entries finds a key by comparing it with each entry in turn, without
+/// hashing it. A larger map hashes the key, and only compares it with entries whose hashes share the same top bits.
+///
+/// Inline entries move whenever another is removed, so handles, cursors and the batched operations are only offered by
+/// `HashMap` itself.
+pub struct SmallHashMap<K: Hash + Eq, V, const N: usize, S: BuildHasher + Default = FxBuildHasher> {
+    repr: Repr<K, V, S, N>,
+}
+
+impl<K: Hash + Eq, V, const N: usize, S: BuildHasher + Default> SmallHashMap<K, V, N, S> {
+    // Private interface
+
+    #[cold]
+    fn spill(&mut self, additional: usize) {
+        if let Repr::Inline(inline) = &mut self.repr {
+            let hasher = mem::take(&mut inline.hasher);
+            let mut map = HashMap::with_capacity_and_hasher(inline.len + additional, hasher);
+            for (key, val) in inline.drain() {
+                map.insert(key, val);
+            }
+            self.repr = Repr::Heap(map);
+        }
+    }
+
+    // Public interface
+
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self { repr: Repr::Inline(Inline::new(Default::default())) }
+    }
+
+    #[inline(always)]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, Default::default())
+    }
+
+    #[inline(always)]
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self {
+            repr: if capacity <= N {
+                Repr::Inline(Inline::new(hasher))
+            } else {
+                Repr::Heap(HashMap::with_capacity_and_hasher(capacity, hasher))
+            },
+        }
+    }
+
+    /// Whether the entries are still held inline.
+    pub fn is_inline(&self) -> bool {
+        match &self.repr {
+            Repr::Inline(_) => true,
+            Repr::Heap(_) => false,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        match &self.repr {
+            Repr::Inline(inline) => &inline.hasher,
+            Repr::Heap(map) => map.hasher(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        match &self.repr {
+            Repr::Inline(_) => N,
+            Repr::Heap(map) => map.capacity(),
+        }
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        match &mut self.repr {
+            Repr::Inline(inline) if inline.len + additional <= N => {},
+            Repr::Inline(_) => self.spill(additional),
+            Repr::Heap(map) => map.reserve(additional),
+        }
+    }
+
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), CollectionAllocErr> {
+        match &mut self.repr {
+            Repr::Heap(map) => map.try_reserve(additional),
+            Repr::Inline(_) => {
+                self.reserve(additional);
+                Ok(())
+            },
+        }
+    }
+
+    /// Free unused space once the entries have moved to the heap. They are never moved back inline.
+    pub fn shrink_to_fit(&mut self) {
+        if let Repr::Heap(map) = &mut self.repr {
+            map.shrink_to_fit();
+        }
+    }
+
+    /// Like `shrink_to_fit`, but keeps room for at least `min_capacity` entries. Panics if the capacity is already
+    /// smaller.
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        match &mut self.repr {
+            Repr::Inline(_) => {
+                if N < min_capacity {
+                    panic!("Current capacity is smaller than supplied minimum capacity");
+                }
+            },
+            Repr::Heap(map) => map.shrink_to(min_capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.repr {
+            Repr::Inline(inline) => inline.len,
+            Repr::Heap(map) => map.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&mut self) {
+        match &mut self.repr {
+            Repr::Inline(inline) => inline.drain().for_each(drop),
+            Repr::Heap(map) => map.clear(),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.get_key_value(key).map(|(_, v)| v)
+    }
+
+    pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
+        match &self.repr {
+            Repr::Inline(inline) => inline.position(key).map(|idx| {
+                let (k, v) = &inline.as_slice()[idx];
+                (k, v)
+            }),
+            Repr::Heap(map) => map.get_key_value(key),
+        }
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        match &mut self.repr {
+            Repr::Inline(inline) => match inline.position(key) {
+                Some(idx) => Some(&mut inline.as_mut_slice()[idx].1),
+                None => None,
+            },
+            Repr::Heap(map) => map.get_mut(key),
+        }
+    }
+
+    pub fn insert(&mut self, key: K, val: V) -> Option<V> {
+        match &mut self.repr {
+            Repr::Inline(inline) => match inline.find(&key) {
+                Ok(idx) => Some(mem::replace(&mut inline.as_mut_slice()[idx].1, val)),
+                Err(tag) if inline.len < N => {
+                    inline.push(tag, key, val);
+                    None
+                },
+                Err(_) => {
+                    self.spill(1);
+                    self.insert(key, val)
+                },
+            },
+            Repr::Heap(map) => map.insert(key, val),
+        }
+    }
+
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, f: F) -> &mut V {
+        if let Repr::Inline(inline) = &self.repr {
+            if inline.len == N && inline.position(&key).is_none() {
+                self.spill(1);
+            }
+        }
+
+        match &mut self.repr {
+            Repr::Inline(inline) => {
+                let idx = match inline.find(&key) {
+                    Ok(idx) => idx,
+                    Err(tag) => {
+                        inline.push(tag, key, f());
+                        inline.len - 1
+                    },
+                };
+                &mut inline.as_mut_slice()[idx].1
+            },
+            Repr::Heap(map) => map.get_or_insert_with(key, f),
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    pub fn remove_entry(&mut self, key: &K) -> Option<(K, V)> {
+        match &mut self.repr {
+            Repr::Inline(inline) => inline.position(key).map(|idx| inline.swap_remove(idx)),
+            Repr::Heap(map) => map.remove_entry(key),
+        }
+    }
+
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut f: F) {
+        match &mut self.repr {
+            Repr::Inline(inline) => {
+                let mut idx = 0;
+                while idx < inline.len {
+                    let (k, v) = &mut inline.as_mut_slice()[idx];
+                    if f(k, v) {
+                        idx += 1;
+                    } else {
+                        drop(inline.swap_remove(idx));
+                    }
+                }
+            },
+            Repr::Heap(map) => map.retain(f),
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.iter().map(|(_, v)| v)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> + '_ {
+        self.iter_mut().map(|(_, v)| v)
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: match &self.repr {
+                Repr::Inline(inline) => IterRepr::Inline(inline.as_slice().iter()),
+                Repr::Heap(map) => IterRepr::Heap(map.iter()),
+            },
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            inner: match &mut self.repr {
+                Repr::Inline(inline) => IterMutRepr::Inline(inline.as_mut_slice().iter_mut()),
+                Repr::Heap(map) => IterMutRepr::Heap(map.iter_mut()),
+            },
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, const N: usize, S: BuildHasher + Default> Default for SmallHashMap<K, V, N, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone, const N: usize, S: BuildHasher + Clone + Default> Clone for SmallHashMap<K, V, N, S> {
+    fn clone(&self) -> Self {
+        Self {
+            repr: match &self.repr {
+                Repr::Inline(inline) => {
+                    let mut new = Inline::new(inline.hasher.clone());
+                    for ((k, v), tag) in inline.as_slice().iter().zip(inline.tags) {
+                        new.push(tag, k.clone(), v.clone());
+                    }
+                    Repr::Inline(new)
+                },
+                Repr::Heap(map) => Repr::Heap(map.clone()),
+            },
+        }
+    }
+}
+
+pub struct Iter<'a, K: 'a, V: 'a> {
+    inner: IterRepr<'a, K, V>,
+}
+
+enum IterRepr<'a, K: 'a, V: 'a> {
+    Inline(slice::Iter<'a, (K, V)>),
+    Heap(crate::Iter<'a, K, V>),
+}
+
+impl<'a, K: 'a, V: 'a> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            IterRepr::Inline(iter) => iter.next().map(|(k, v)| (k, v)),
+            IterRepr::Heap(iter) => iter.next(),
+        }
+    }
+}
+
+pub struct IterMut<'a, K: 'a, V: 'a> {
+    inner: IterMutRepr<'a, K, V>,
+}
+
+enum IterMutRepr<'a, K: 'a, V: 'a> {
+    Inline(slice::IterMut<'a, (K, V)>),
+    Heap(crate::IterMut<'a, K, V>),
+}
+
+impl<'a, K: 'a, V: 'a> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            IterMutRepr::Inline(iter) => iter.next().map(|(k, v)| (&*k, v)),
+            IterMutRepr::Heap(iter) => iter.next(),
+        }
+    }
+}
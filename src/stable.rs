@@ -0,0 +1,240 @@
+use std::{
+    hash::{BuildHasher, Hash},
+    mem,
+    pin::Pin,
+    ptr,
+};
+
+use fxhash::FxBuildHasher;
+use allocator_api::RawVec;
+
+use crate::{HashMap, RawVecGetSet};
+
+// The number of values in each arena chunk
+const CHUNK_LEN: usize = 64;
+
+// Value storage made of fixed-size chunks. Chunks are never resized or freed before the arena is, so a value keeps its
+// address from the moment that it is allocated until it is taken or dropped.
+struct Arena<V> {
+    chunks: Vec<RawVec<V>>,
+    free: Vec<usize>, // Slots that have been vacated and may be reused
+    used: usize, // Slots that have ever been handed out
+}
+
+// Like `HashMap`, the arena uniquely owns its values
+unsafe impl<V: Send> Send for Arena<V> {}
+unsafe impl<V: Sync> Sync for Arena<V> {}
+
+impl<V> Arena<V> {
+    fn new() -> Self {
+        Self {
+            chunks: Vec::new(),
+            free: Vec::new(),
+            used: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn alloc(&mut self, val: V) -> usize {
+        let slot = match self.free.pop() {
+            Some(slot) => slot,
+            None => {
+                if self.used == self.chunks.len() * CHUNK_LEN {
+                    self.chunks.push(RawVec::with_capacity(CHUNK_LEN));
+                }
+                self.used += 1;
+                self.used - 1
+            },
+        };
+        unsafe { self.chunks[slot / CHUNK_LEN].set(slot % CHUNK_LEN, val) };
+        slot
+    }
+
+    // The following require that `slot` is occupied
+
+    #[inline(always)]
+    unsafe fn get_ref(&self, slot: usize) -> &V {
+        self.chunks[slot / CHUNK_LEN].get_ref(slot % CHUNK_LEN)
+    }
+
+    #[inline(always)]
+    unsafe fn get_mut(&mut self, slot: usize) -> &mut V {
+        self.chunks[slot / CHUNK_LEN].get_mut(slot % CHUNK_LEN)
+    }
+
+    #[inline(always)]
+    unsafe fn take(&mut self, slot: usize) -> V {
+        self.free.push(slot);
+        self.chunks[slot / CHUNK_LEN].get(slot % CHUNK_LEN)
+    }
+
+    #[inline(always)]
+    unsafe fn drop_in_place(&mut self, slot: usize) {
+        self.free.push(slot);
+        ptr::drop_in_place(self.get_mut(slot));
+    }
+
+    // Drop the value where it lies, as a pinned value must be, and put `val` at the same address
+    #[inline(always)]
+    unsafe fn replace_in_place(&mut self, slot: usize, val: V) {
+        ptr::drop_in_place(self.get_mut(slot));
+        self.chunks[slot / CHUNK_LEN].set(slot % CHUNK_LEN, val);
+    }
+
+    fn clear(&mut self) {
+        self.chunks.clear();
+        self.free.clear();
+        self.used = 0;
+    }
+}
+
+/// A map whose values never move once inserted, no matter how the table is resized. Values are kept in a chunked
+/// arena and the table only stores their positions within it.
+///
+/// Because values are never moved, they may be pinned with `get_pinned`. Operations that could move a value out of the
+/// map or expose it mutably are only available when `V: Unpin`.
+pub struct StableHashMap<K: Hash + Eq, V, S: BuildHasher + Default = FxBuildHasher> {
+    table: HashMap<K, usize, S>,
+    arena: Arena<V>,
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Default> StableHashMap<K, V, S> {
+    pub fn new() -> Self {
+        Self::with_capacity_and_hasher(0, Default::default())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, Default::default())
+    }
+
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self {
+            table: HashMap::with_capacity_and_hasher(capacity, hasher),
+            arena: Arena::new(),
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        self.table.hasher()
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        for (_, slot) in self.table.iter() {
+            unsafe { ptr::drop_in_place(self.arena.get_mut(*slot)) };
+        }
+        self.table.clear();
+        self.arena.clear();
+    }
+
+    /// Insert a value, returning the previous one. The new value takes over the previous value's address.
+    pub fn insert(&mut self, key: K, val: V) -> Option<V> where V: Unpin {
+        match self.table.get(&key) {
+            Some(slot) => Some(mem::replace(unsafe { self.arena.get_mut(*slot) }, val)),
+            None => {
+                let slot = self.arena.alloc(val);
+                self.table.insert(key, slot);
+                None
+            },
+        }
+    }
+
+    /// Insert a value and pin it, dropping any previous value in place.
+    pub fn insert_pinned(&mut self, key: K, val: V) -> Pin<&mut V> {
+        let slot = match self.table.get(&key) {
+            Some(&slot) => {
+                unsafe { self.arena.replace_in_place(slot, val) };
+                slot
+            },
+            None => {
+                let slot = self.arena.alloc(val);
+                self.table.insert(key, slot);
+                slot
+            },
+        };
+        unsafe { Pin::new_unchecked(self.arena.get_mut(slot)) }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.table.get(key).map(|slot| unsafe { self.arena.get_ref(*slot) })
+    }
+
+    pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
+        self.table
+            .get_key_value(key)
+            .map(|(key, slot)| (key, unsafe { self.arena.get_ref(*slot) }))
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> where V: Unpin {
+        let slot = *self.table.get(key)?;
+        Some(unsafe { self.arena.get_mut(slot) })
+    }
+
+    /// Get a pinned reference to a value. The value will not move until it is removed from the map.
+    pub fn get_pinned(&mut self, key: &K) -> Option<Pin<&mut V>> {
+        let slot = *self.table.get(key)?;
+        Some(unsafe { Pin::new_unchecked(self.arena.get_mut(slot)) })
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.table.contains_key(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> where V: Unpin {
+        self.table
+            .remove(key)
+            .map(|slot| unsafe { self.arena.take(slot) })
+    }
+
+    pub fn remove_entry(&mut self, key: &K) -> Option<(K, V)> where V: Unpin {
+        self.table
+            .remove_entry(key)
+            .map(|(key, slot)| (key, unsafe { self.arena.take(slot) }))
+    }
+
+    /// Remove a value without moving it, dropping it in place. Returns whether the key was present.
+    pub fn delete(&mut self, key: &K) -> bool {
+        match self.table.remove(key) {
+            Some(slot) => {
+                unsafe { self.arena.drop_in_place(slot) };
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.table.keys()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.table.values().map(move |slot| unsafe { self.arena.get_ref(*slot) })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.table
+            .iter()
+            .map(move |(key, slot)| (key, unsafe { self.arena.get_ref(*slot) }))
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Default> Drop for StableHashMap<K, V, S> {
+    fn drop(&mut self) {
+        for (_, slot) in self.table.iter() {
+            unsafe { ptr::drop_in_place(self.arena.get_mut(*slot)) };
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Default> Default for StableHashMap<K, V, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
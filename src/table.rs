@@ -0,0 +1,216 @@
+use std::mem;
+
+use crate::raw::RawTable;
+
+/// A low-level table of values, built on the same `RawTable` as `HashMap`, for when the key is some projection of the
+/// value. Rather than the table hashing and comparing keys itself, each operation is given the hash and an `eq`
+/// closure by the caller.
+///
+/// The table keeps the hash that each value was inserted with, so values are never rehashed when the table grows or
+/// shifts them, and `eq` is only called for values whose hash matches.
+pub struct HashTable<T> {
+    table: RawTable<T>,
+}
+
+impl<T> HashTable<T> {
+    // Private interface
+
+    #[inline(always)]
+    fn probe_idx(&self, hash: u64, eq: impl FnMut(&T) -> bool) -> Result<usize, usize> {
+        self.table.probe(hash, eq)
+    }
+
+    #[inline(always)]
+    fn remove_idx(&mut self, idx: usize) -> T {
+        let (val, ()) = self.table.remove_at(idx);
+        self.table.try_shrink();
+        val
+    }
+
+    // The following require that `idx` is occupied
+
+    #[inline(always)]
+    unsafe fn get_ref(&self, idx: usize) -> &T {
+        self.table.key_ref(idx)
+    }
+
+    #[inline(always)]
+    unsafe fn get_mut(&mut self, idx: usize) -> &mut T {
+        &mut *self.table.key_ptr(idx)
+    }
+
+    // Public interface
+
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            table: RawTable::with_capacity(capacity),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.table.capacity()
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.table.reserve(additional);
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.table.shrink_to(0);
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+
+    /// Find a value with the given hash that satisfies `eq`.
+    pub fn find(&self, hash: u64, eq: impl FnMut(&T) -> bool) -> Option<&T> {
+        self.probe_idx(hash, eq).ok().map(|idx| unsafe { self.get_ref(idx) })
+    }
+
+    pub fn find_mut(&mut self, hash: u64, eq: impl FnMut(&T) -> bool) -> Option<&mut T> {
+        match self.probe_idx(hash, eq) {
+            Ok(idx) => Some(unsafe { self.get_mut(idx) }),
+            Err(_) => None,
+        }
+    }
+
+    /// Find the value with the given hash that satisfies `eq`, or the place where such a value would be inserted.
+    pub fn entry(&mut self, hash: u64, mut eq: impl FnMut(&T) -> bool) -> Entry<'_, T> {
+        let mut hint = match self.probe_idx(hash, &mut eq) {
+            Ok(idx) => return Entry::Occupied(OccupiedEntry { table: self, idx }),
+            Err(hint) => hint,
+        };
+
+        // Growing moves the values, so the hint must then be found again
+        if self.table.try_grow() {
+            hint = self.probe_idx(hash, eq).unwrap_err();
+        }
+        Entry::Vacant(VacantEntry { table: self, hint, hash })
+    }
+
+    /// Insert a value without checking whether an equal value is already present.
+    pub fn insert_unique(&mut self, hash: u64, val: T) -> &mut T {
+        let bucket = self.table.insert(hash, val, ());
+        self.table.get_mut(bucket).unwrap().0
+    }
+
+    pub fn remove(&mut self, hash: u64, eq: impl FnMut(&T) -> bool) -> Option<T> {
+        match self.probe_idx(hash, eq) {
+            Ok(idx) => Some(self.remove_idx(idx)),
+            Err(_) => None,
+        }
+    }
+
+    pub fn retain(&mut self, mut f: impl FnMut(&mut T) -> bool) {
+        self.table.retain(|val, ()| f(val));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.table.iter().map(move |bucket| self.table.get(bucket).unwrap().0)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> + '_ {
+        let table = &self.table;
+        (0..table.cap).filter_map(move |idx| table.key(idx).map(|_| unsafe { &mut *table.key_ptr(idx) }))
+    }
+}
+
+impl<T> Default for HashTable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Clone for HashTable<T> {
+    fn clone(&self) -> Self {
+        Self {
+            table: self.table.clone(),
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.table.clone_from(&source.table);
+    }
+}
+
+pub enum Entry<'a, T> {
+    Occupied(OccupiedEntry<'a, T>),
+    Vacant(VacantEntry<'a, T>),
+}
+
+impl<'a, T> Entry<'a, T> {
+    pub fn or_insert(self, default: T) -> &'a mut T {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> T>(self, default: F) -> &'a mut T {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn and_modify<F: FnOnce(&mut T)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+pub struct OccupiedEntry<'a, T> {
+    table: &'a mut HashTable<T>,
+    idx: usize,
+}
+
+impl<'a, T> OccupiedEntry<'a, T> {
+    pub fn get(&self) -> &T {
+        unsafe { self.table.get_ref(self.idx) }
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { self.table.get_mut(self.idx) }
+    }
+
+    pub fn into_mut(self) -> &'a mut T {
+        let OccupiedEntry { table, idx } = self;
+        unsafe { table.get_mut(idx) }
+    }
+
+    /// Replace the value, which must still have the same hash and be equal to the old one.
+    pub fn insert(&mut self, val: T) -> T {
+        mem::replace(self.get_mut(), val)
+    }
+
+    pub fn remove(self) -> T {
+        self.table.remove_idx(self.idx)
+    }
+}
+
+pub struct VacantEntry<'a, T> {
+    table: &'a mut HashTable<T>,
+    hint: usize,
+    hash: u64,
+}
+
+impl<'a, T> VacantEntry<'a, T> {
+    pub fn insert(self, val: T) -> &'a mut T {
+        let VacantEntry { table, hint, hash } = self;
+        // The strategy may not put the new value at the hint, so its slot is only known once it has been placed
+        let idx = table.table.insert_at(hint, hash, val, ());
+        unsafe { table.get_mut(idx) }
+    }
+}
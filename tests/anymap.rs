@@ -0,0 +1,102 @@
+use std::{any::Any, rc::Rc, thread};
+
+use smash::{
+    AnyMap, SendSyncAnyMap,
+    anymap::Entry,
+};
+
+#[derive(Debug, PartialEq, Default)]
+struct Pos(i32, i32);
+
+#[test]
+fn one_value_per_type() {
+    let mut map = AnyMap::new();
+    assert!(map.is_empty());
+    assert_eq!(map.insert(5u32), None);
+    assert_eq!(map.insert(Pos(1, 2)), None);
+    assert_eq!(map.insert(Rc::new("rc")), None);
+    assert_eq!(map.insert(7u32), Some(5));
+    assert_eq!(map.len(), 3);
+
+    // Types that look alike are still distinct
+    assert_eq!(map.get::<u32>(), Some(&7));
+    assert_eq!(map.get::<u64>(), None);
+    assert_eq!(map.get::<i32>(), None);
+    assert_eq!(map.get::<Rc<&str>>().map(|rc| **rc), Some("rc"));
+
+    map.get_mut::<Pos>().unwrap().0 = 9;
+    assert_eq!(map.get::<Pos>(), Some(&Pos(9, 2)));
+    assert!(map.contains::<Pos>());
+    assert!(!map.contains::<String>());
+
+    assert_eq!(map.remove::<u32>(), Some(7));
+    assert_eq!(map.remove::<u32>(), None);
+    assert_eq!(map.len(), 2);
+
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(map.get::<Pos>(), None);
+}
+
+#[test]
+fn entries() {
+    let mut map = AnyMap::<dyn Any>::with_capacity(4);
+    assert!(map.capacity() >= 4);
+    *map.entry::<u64>().or_insert(1) += 1;
+    *map.entry::<u64>().or_insert(1) += 1;
+    assert_eq!(map.get::<u64>(), Some(&3));
+
+    map.entry::<String>().or_default().push_str("hi");
+    map.entry::<String>().and_modify(|s| s.push('!')).or_default();
+    assert_eq!(map.get::<String>().map(String::as_str), Some("hi!"));
+    assert_eq!(*map.entry::<Pos>().or_insert_with(|| Pos(1, 1)), Pos(1, 1));
+
+    match map.entry::<Pos>() {
+        Entry::Occupied(mut entry) => {
+            assert_eq!(entry.insert(Pos(2, 2)), Pos(1, 1));
+            entry.get_mut().0 = 3;
+            assert_eq!(entry.get(), &Pos(3, 2));
+            assert_eq!(entry.remove(), Pos(3, 2));
+        },
+        Entry::Vacant(_) => panic!("the value was just inserted"),
+    }
+    match map.entry::<Pos>() {
+        Entry::Occupied(_) => panic!("the value was just removed"),
+        Entry::Vacant(entry) => assert_eq!(*entry.insert(Pos(4, 4)), Pos(4, 4)),
+    }
+    assert_eq!(map.len(), 3);
+}
+
+// Looking up a value that is already present never grows the map, even when it is full
+#[test]
+fn entry_when_full() {
+    let mut map = AnyMap::<dyn Any>::with_capacity(4);
+    map.insert(1u8);
+    map.insert(2u16);
+    map.insert(3u32);
+    map.insert(4u64);
+    let cap = map.capacity();
+    assert_eq!(map.len(), cap);
+
+    *map.entry::<u8>().or_insert(0) += 1;
+    assert_eq!(map.capacity(), cap);
+    assert_eq!(map.get::<u8>(), Some(&2));
+
+    // A new value still makes room for itself, and every value is found after the move
+    assert_eq!(*map.entry::<i8>().or_insert(5), 5);
+    assert!(map.capacity() > cap);
+    assert_eq!((map.get::<u16>(), map.get::<u32>(), map.get::<u64>()), (Some(&2), Some(&3), Some(&4)));
+}
+
+#[test]
+fn send_sync() {
+    let mut map = SendSyncAnyMap::new();
+    map.insert(vec![1u8]);
+    map.insert("hello");
+    thread::spawn(move || {
+        assert_eq!(map.get::<Vec<u8>>(), Some(&vec![1]));
+        assert_eq!(map.get::<&str>(), Some(&"hello"));
+    })
+    .join()
+    .unwrap();
+}
@@ -0,0 +1,108 @@
+use std::{rc::Rc, sync::Mutex};
+
+use smash::{ArrayHashMap, CapacityFull};
+
+static GLOBAL: Mutex<ArrayHashMap<u32, u32, 16>> = Mutex::new(ArrayHashMap::new());
+
+#[test]
+fn statics() {
+    GLOBAL.lock().unwrap().insert(1, 2).unwrap();
+    assert_eq!(GLOBAL.lock().unwrap().get(&1), Some(&2));
+    assert_eq!(GLOBAL.lock().unwrap().capacity(), 16);
+}
+
+#[test]
+fn full() {
+    let mut map = ArrayHashMap::<u32, String, 8>::new();
+    assert!(map.is_empty());
+    for i in 0..8 {
+        assert_eq!(map.insert(i, i.to_string()), Ok(None));
+    }
+    assert!(map.is_full());
+
+    // A new key is handed back, but an existing one can still be replaced
+    assert_eq!(map.insert(99, "x".to_string()), Err(CapacityFull(99, "x".to_string())));
+    assert_eq!(map.insert(3, "y".to_string()), Ok(Some("3".to_string())));
+    for i in 0..8 {
+        assert!(map.contains_key(&i));
+    }
+
+    assert_eq!(map.remove(&3), Some("y".to_string()));
+    assert!(!map.is_full());
+    assert_eq!(map.insert(99, "x".to_string()), Ok(None));
+    assert_eq!(map.iter().count(), 8);
+    assert_eq!(map.get_key_value(&99), Some((&99, &"x".to_string())));
+
+    for val in map.values_mut() {
+        val.push('!');
+    }
+    for (key, val) in map.iter_mut() {
+        val.insert_str(0, &key.to_string());
+    }
+    assert_eq!(map.get(&99).map(String::as_str), Some("99x!"));
+    *map.get_mut(&0).unwrap() = "zero".to_string();
+
+    let clone = map.clone();
+    assert_eq!(clone.len(), 8);
+    for (key, val) in map.iter() {
+        assert_eq!(clone.get(key), Some(val));
+    }
+    assert_eq!(clone.keys().count(), clone.values().count());
+
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(map.get(&99), None);
+    assert_eq!(clone.get(&0).map(String::as_str), Some("zero"));
+}
+
+// Mixed operations, checked against the standard library's map, with more keys than the map has room for
+#[test]
+fn random() {
+    let mut map = ArrayHashMap::<u16, u64, 64>::new();
+    let mut reference = std::collections::HashMap::new();
+    let mut state = 0x2545_F491_u64;
+    for _ in 0..200_000 {
+        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        let key = ((state >> 33) % 90) as u16;
+        if state >> 62 == 0 {
+            assert_eq!(map.remove(&key), reference.remove(&key));
+        } else {
+            match map.insert(key, state) {
+                Ok(old) => assert_eq!(old, reference.insert(key, state)),
+                Err(CapacityFull(k, v)) => {
+                    assert_eq!((k, v), (key, state));
+                    assert!(reference.len() == 64 && !reference.contains_key(&key));
+                },
+            }
+        }
+        assert_eq!(map.len(), reference.len());
+    }
+    for key in 0..90 {
+        assert_eq!(map.get(&key), reference.get(&key));
+    }
+}
+
+#[test]
+fn drops() {
+    let rc = Rc::new(());
+    let mut map = ArrayHashMap::<u32, Rc<()>, 32>::new();
+    for i in 0..20 {
+        map.insert(i, rc.clone()).unwrap();
+    }
+    let clone = map.clone();
+    assert_eq!(Rc::strong_count(&rc), 41);
+    drop(clone);
+
+    map.remove(&0);
+    assert_eq!(Rc::strong_count(&rc), 20);
+    assert!(map.insert(1, rc.clone()).unwrap().is_some());
+    assert_eq!(Rc::strong_count(&rc), 20);
+    drop(map);
+    assert_eq!(Rc::strong_count(&rc), 1);
+}
+
+#[test]
+#[should_panic(expected = "power of two")]
+fn capacity_not_power_of_two() {
+    let _ = ArrayHashMap::<u8, u8, 6>::new();
+}
@@ -0,0 +1,88 @@
+use fxhash::FxBuildHasher;
+use smash::{
+    HashMap,
+    layout::{AoS, CacheLine, Layout, SoA},
+    probe::{Hopscotch, Linear, ProbeStrategy, Quadratic, RobinHood, Swiss},
+};
+
+fn batches<P: ProbeStrategy, L: Layout>() {
+    let mut map = HashMap::<u64, String, FxBuildHasher, P, L>::new();
+    assert_eq!(map.get_many(&[1, 2]), [None, None]);
+    assert_eq!(map.contains_many(&[1, 2]), [false, false]);
+    assert_eq!(map.remove_many(&[1, 2]), [None, None]);
+
+    let old = map.insert_many((0..1000).map(|i| (i, i.to_string())));
+    assert_eq!(old.len(), 1000);
+    assert!(old.iter().all(Option::is_none));
+    assert_eq!(map.len(), 1000);
+
+    // Some keys are replaced, some are new, and some appear twice
+    let old = map.insert_many((990..1010).chain(1005..1010).map(|i| (i, format!("n{}", i))));
+    for (n, (i, old)) in (990..1010).chain(1005..1010).zip(old).enumerate() {
+        let expected = match i {
+            0..=999 => Some(i.to_string()),
+            _ if n >= 20 => Some(format!("n{}", i)),
+            _ => None,
+        };
+        assert_eq!(old, expected);
+    }
+    assert_eq!(map.len(), 1010);
+
+    let keys = (0..2000).rev().collect::<Vec<_>>();
+    for (key, val) in keys.iter().zip(map.get_many(&keys)) {
+        let expected = match key {
+            0..=989 => Some(key.to_string()),
+            990..=1009 => Some(format!("n{}", key)),
+            _ => None,
+        };
+        assert_eq!(val.cloned(), expected);
+    }
+    for (key, found) in keys.iter().zip(map.contains_many(&keys)) {
+        assert_eq!(found, *key < 1010);
+    }
+
+    // Keys that are removed twice are only found the first time
+    let evens = (0..2000).step_by(2).chain([0, 2]).collect::<Vec<_>>();
+    for (i, (key, val)) in evens.iter().zip(map.remove_many(&evens)).enumerate() {
+        assert_eq!(val.is_some(), *key < 1010 && i < 1000);
+    }
+    assert_eq!(map.len(), 505);
+    for key in 0..1010 {
+        assert_eq!(map.contains_key(&key), key % 2 == 1);
+    }
+}
+
+#[test]
+fn layouts() {
+    batches::<RobinHood, SoA>();
+    batches::<RobinHood, AoS>();
+    batches::<RobinHood, CacheLine>();
+}
+
+#[test]
+fn strategies() {
+    batches::<Linear, SoA>();
+    batches::<Quadratic, AoS>();
+    batches::<Hopscotch, CacheLine>();
+    batches::<Swiss, SoA>();
+}
+
+#[test]
+fn insert_many_reserves_for_missing_keys() {
+    let mut map = HashMap::<u64, u64>::new();
+    map.insert_many((0..1000).map(|i| (i, i)));
+    let capacity = map.capacity();
+
+    // Replacing every value needs no room
+    map.insert_many((0..1000).map(|i| (i, i + 1)));
+    assert_eq!(map.capacity(), capacity);
+
+    // Nor does a long run of a few keys
+    map.insert_many((0..100_000).map(|i| (i % 10, i)));
+    assert_eq!(map.capacity(), capacity);
+    assert_eq!(map.len(), 1000);
+
+    let mut map = HashMap::<u64, u64>::new();
+    map.insert_many((0..100_000).map(|i| (i % 10, i)));
+    assert!(map.capacity() < 64);
+}
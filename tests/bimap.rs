@@ -0,0 +1,101 @@
+use smash::{BiMap, Overwritten};
+
+#[test]
+fn overwritten() {
+    let mut map = BiMap::<u32, String>::new();
+    for i in 0..1000 {
+        assert_eq!(map.insert(i, i.to_string()), Overwritten::Neither);
+    }
+    assert_eq!(map.len(), 1000);
+    assert_eq!(map.get_by_left(&5).map(String::as_str), Some("5"));
+    assert_eq!(map.get_by_right(&"7".to_string()), Some(&7));
+    assert!(map.contains_left(&999));
+    assert!(!map.contains_right(&"1000".to_string()));
+
+    assert_eq!(map.insert(5, "5".to_string()), Overwritten::Pair(5, "5".to_string()));
+    assert_eq!(map.insert(5, "x".to_string()), Overwritten::Left(5, "5".to_string()));
+    assert_eq!(map.insert(2000, "x".to_string()), Overwritten::Right(5, "x".to_string()));
+    assert_eq!(map.insert(1, "2".to_string()), Overwritten::Both((1, "1".to_string()), (2, "2".to_string())));
+    assert_eq!(map.len(), 999);
+    assert_eq!(map.get_by_left(&5), None);
+    assert_eq!(map.get_by_right(&"1".to_string()), None);
+
+    for i in 3..1000 {
+        if i != 5 {
+            assert_eq!(map.remove_by_left(&i), Some((i, i.to_string())));
+        }
+    }
+    assert_eq!(map.remove_by_left(&3), None);
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get_by_right(&"2".to_string()), Some(&1));
+    assert_eq!(map.remove_by_right(&"x".to_string()), Some((2000, "x".to_string())));
+    assert_eq!(map.remove_by_right(&"x".to_string()), None);
+    let mut pairs = map.iter().collect::<Vec<_>>();
+    pairs.sort();
+    assert_eq!(pairs, [(&0, &"0".to_string()), (&1, &"2".to_string())]);
+    assert_eq!(map.left_values().count(), 2);
+    assert_eq!(map.right_values().count(), 2);
+
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(map.get_by_left(&1), None);
+}
+
+// Mixed operations, checked against a pair of standard library maps that are kept in step
+#[test]
+fn random() {
+    let mut map = BiMap::<u64, u64>::new();
+    let mut by_left = std::collections::HashMap::new();
+    let mut by_right = std::collections::HashMap::new();
+    let mut state = 0x2545_F491_u64;
+    for _ in 0..20_000 {
+        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        let left = (state >> 33) % 300;
+        let right = (state >> 13) % 300;
+        match state >> 62 {
+            0 => {
+                let expected = by_left.remove(&left).map(|right| (left, right));
+                if let Some((_, right)) = expected {
+                    by_right.remove(&right);
+                }
+                assert_eq!(map.remove_by_left(&left), expected);
+            },
+            1 => {
+                let expected = by_right.remove(&right).map(|left| (left, right));
+                if let Some((left, _)) = expected {
+                    by_left.remove(&left);
+                }
+                assert_eq!(map.remove_by_right(&right), expected);
+            },
+            _ => {
+                let old_right = by_left.get(&left).copied();
+                let old_left = by_right.get(&right).copied();
+                let expected = match (old_right, old_left) {
+                    (None, None) => Overwritten::Neither,
+                    (Some(r), Some(l)) if r == right && l == left => Overwritten::Pair(left, right),
+                    (Some(r), Some(l)) => Overwritten::Both((left, r), (l, right)),
+                    (Some(r), None) => Overwritten::Left(left, r),
+                    (None, Some(l)) => Overwritten::Right(l, right),
+                };
+                if let Some(r) = old_right {
+                    by_right.remove(&r);
+                }
+                if let Some(l) = old_left {
+                    by_left.remove(&l);
+                }
+                by_left.insert(left, right);
+                by_right.insert(right, left);
+                assert_eq!(map.insert(left, right), expected);
+            },
+        }
+        assert_eq!(map.len(), by_left.len());
+    }
+
+    for i in 0..300 {
+        assert_eq!(map.get_by_left(&i), by_left.get(&i));
+        assert_eq!(map.get_by_right(&i), by_right.get(&i));
+    }
+    for (left, right) in map.iter() {
+        assert_eq!(by_left[left], *right);
+    }
+}
@@ -0,0 +1,108 @@
+use std::rc::Rc;
+
+use fxhash::FxBuildHasher;
+use smash::{
+    HashMap,
+    layout::{AoS, CacheLine, Layout, SoA},
+    probe::{Hopscotch, Linear, ProbeStrategy, RobinHood, Swiss},
+};
+
+type Map<K, V, P, L> = HashMap<K, V, FxBuildHasher, P, L>;
+
+// Keys and values whose bytes are copied
+fn copy<P: ProbeStrategy, L: Layout>() {
+    let mut source = Map::<u64, u64, P, L>::new();
+    for i in 0..5000 {
+        source.insert(i, i * 3);
+    }
+    for i in 0..1000 {
+        source.remove(&(i * 2));
+    }
+
+    let clone = source.clone();
+    assert_eq!(clone.len(), source.len());
+    assert_eq!(clone.capacity(), source.capacity());
+    for i in 0..5000 {
+        assert_eq!(clone.get(&i), source.get(&i));
+    }
+
+    // A map of the same capacity keeps its allocation
+    let mut dest = source.clone();
+    *dest.get_mut(&1).unwrap() = 0;
+    dest.insert(10_000, 0);
+    let before = dest.get_key_value(&1).unwrap().0 as *const u64;
+    dest.clone_from(&source);
+    assert_eq!(dest.get_key_value(&1).unwrap().0 as *const u64, before);
+    assert_eq!(dest.len(), source.len());
+    assert_eq!(dest.get(&10_000), None);
+    for i in 0..5000 {
+        assert_eq!(dest.get(&i), source.get(&i));
+    }
+
+    // A map of a different capacity takes on the source's
+    let mut dest = Map::<u64, u64, P, L>::new();
+    for i in 0..7 {
+        dest.insert(100_000 + i, 0);
+    }
+    dest.clone_from(&source);
+    assert_eq!(dest.capacity(), source.capacity());
+    assert_eq!(dest.get(&100_000), None);
+    for i in 0..5000 {
+        assert_eq!(dest.get(&i), source.get(&i));
+    }
+
+    dest.clone_from(&Map::new());
+    assert!(dest.is_empty());
+    dest.insert(5, 5);
+    assert_eq!(dest.get(&5), Some(&5));
+}
+
+// Values that must be cloned and dropped one at a time
+fn not_copy<P: ProbeStrategy, L: Layout>() {
+    let rc = Rc::new(());
+    let mut source = Map::<u64, Rc<()>, P, L>::new();
+    for i in 0..100 {
+        source.insert(i, rc.clone());
+    }
+
+    let mut dest = source.clone();
+    assert_eq!(Rc::strong_count(&rc), 201);
+    assert!(dest.values().all(|v| Rc::ptr_eq(v, &rc)));
+
+    // The same capacity, so the allocation is kept and its old entries dropped
+    dest.remove(&0);
+    let before = dest.get_key_value(&1).unwrap().0 as *const u64;
+    dest.clone_from(&source);
+    assert_eq!(dest.get_key_value(&1).unwrap().0 as *const u64, before);
+    assert_eq!(Rc::strong_count(&rc), 201);
+
+    // A different capacity
+    let mut small = Map::<u64, Rc<()>, P, L>::new();
+    small.insert(1, rc.clone());
+    dest.clone_from(&small);
+    assert_eq!(dest.len(), 1);
+    assert_eq!(Rc::strong_count(&rc), 103);
+
+    drop((source, dest, small));
+    assert_eq!(Rc::strong_count(&rc), 1);
+}
+
+#[test]
+fn layouts() {
+    copy::<RobinHood, SoA>();
+    copy::<RobinHood, AoS>();
+    copy::<RobinHood, CacheLine>();
+    not_copy::<RobinHood, SoA>();
+    not_copy::<RobinHood, AoS>();
+    not_copy::<RobinHood, CacheLine>();
+}
+
+#[test]
+fn strategies() {
+    copy::<Linear, SoA>();
+    copy::<Swiss, CacheLine>();
+    copy::<Hopscotch, AoS>();
+    not_copy::<Linear, AoS>();
+    not_copy::<Swiss, SoA>();
+    not_copy::<Hopscotch, CacheLine>();
+}
@@ -0,0 +1,77 @@
+// Hashers shared by the tests, for placing keys in chosen slots. Each test uses only some of them.
+#![allow(dead_code)]
+
+use std::hash::{BuildHasherDefault, Hasher};
+
+// Fold arbitrary bytes into a hasher's state, for keys that are not written as a single `u64`
+fn fold(state: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(state, |state, &byte| state.rotate_left(8) ^ byte as u64)
+}
+
+// Hashes integers to themselves, so that tests can choose where keys are placed
+#[derive(Default)]
+pub struct IdentityHasher(u64);
+
+impl Hasher for IdentityHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0 = fold(self.0, bytes);
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = n;
+    }
+}
+
+pub type Identity = BuildHasherDefault<IdentityHasher>;
+
+// Hashes groups of four consecutive integers to the same value
+#[derive(Default)]
+pub struct WeakHasher(u64);
+
+impl Hasher for WeakHasher {
+    fn finish(&self) -> u64 {
+        (self.0 / 4).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0 = fold(self.0, bytes);
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = n;
+    }
+}
+
+// Hashes everything to the same value
+#[derive(Default)]
+pub struct CollidingHasher;
+
+impl Hasher for CollidingHasher {
+    fn finish(&self) -> u64 {
+        7
+    }
+
+    fn write(&mut self, _bytes: &[u8]) {}
+}
+
+// Hashes integers into the last few slots of any table, so that their probe sequences wrap around to the start
+#[derive(Default)]
+pub struct TailHasher(u64);
+
+impl Hasher for TailHasher {
+    fn finish(&self) -> u64 {
+        !(self.0 % 5)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0 = fold(self.0, bytes);
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = n;
+    }
+}
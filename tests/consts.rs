@@ -0,0 +1,92 @@
+use std::{
+    hash::{BuildHasher, Hasher},
+    sync::Mutex,
+};
+
+use fxhash::FxHasher;
+use smash::{
+    HashMap,
+    layout::AoS,
+    probe::Swiss,
+    smash_map,
+    smash_set,
+};
+
+// A hasher with no `Default`, so maps that use it can only be built with `with_hasher`
+#[derive(Clone)]
+struct Seeded(u64);
+
+impl BuildHasher for Seeded {
+    type Hasher = FxHasher;
+
+    fn build_hasher(&self) -> FxHasher {
+        let mut hasher = FxHasher::default();
+        hasher.write_u64(self.0);
+        hasher
+    }
+}
+
+static GLOBAL: Mutex<HashMap<u32, String, Seeded>> = Mutex::new(HashMap::with_hasher(Seeded(7)));
+
+const EMPTY: HashMap<u32, u32, Seeded, Swiss, AoS> = HashMap::with_hasher(Seeded(7));
+
+#[test]
+fn static_map() {
+    GLOBAL.lock().unwrap().insert(1, "one".to_string());
+    GLOBAL.lock().unwrap().insert(2, "two".to_string());
+    let global = GLOBAL.lock().unwrap();
+    assert_eq!(global.get(&1).map(String::as_str), Some("one"));
+    assert_eq!(global.len(), 2);
+}
+
+#[test]
+fn const_map() {
+    let mut map = EMPTY;
+    assert!(map.is_empty());
+    assert_eq!(map.capacity(), 0);
+    for i in 0..1000 {
+        map.insert(i, i);
+    }
+    for i in 0..500 {
+        assert_eq!(map.remove(&i), Some(i));
+    }
+    assert_eq!(map.len(), 500);
+    assert_eq!(map.get(&999), Some(&999));
+
+    // Every constant is a fresh, empty map
+    assert!(EMPTY.is_empty());
+
+    // The hasher needs no `Default` to be cloned, either
+    let clone = map.clone();
+    assert_eq!(clone.len(), 500);
+}
+
+#[test]
+fn map_literal() {
+    let map = smash_map! { "a" => 1, "b" => 2, "c" => 3, };
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get(&"c"), Some(&3));
+
+    // Pre-sized for the entries, without growing
+    let big = smash_map! { 0 => 0, 1 => 1, 2 => 2, 3 => 3, 4 => 4, 5 => 5, 6 => 6, 7 => 7, 8 => 8 };
+    assert_eq!(big.capacity(), 16);
+
+    let empty: HashMap<u8, u8> = smash_map! {};
+    assert!(empty.is_empty());
+
+    // Later entries replace earlier ones with equal keys
+    let repeated = smash_map! { 1 => "a", 1 => "b" };
+    assert_eq!(repeated.len(), 1);
+    assert_eq!(repeated.get(&1), Some(&"b"));
+}
+
+#[test]
+fn set_literal() {
+    let set = smash_set! { 'a', 'b', 'b', 'c' };
+    assert_eq!(set.len(), 3);
+    assert!(set.contains_key(&'b'));
+    assert!(!set.contains_key(&'d'));
+
+    let empty: HashMap<char, ()> = smash_set! {};
+    assert!(empty.is_empty());
+}
@@ -0,0 +1,64 @@
+use smash::Counter;
+
+#[test]
+fn words() {
+    let mut counter = "a b c a b a d e a b".split(' ').collect::<Counter<&str>>();
+    assert_eq!(counter.len(), 5);
+    assert_eq!(counter.total(), 10);
+    assert_eq!(counter.get(&"a"), 4);
+    assert_eq!(counter.get(&"z"), 0);
+    assert_eq!(counter.most_common(2), [(&"a", 4), (&"b", 3)]);
+    assert_eq!(counter.most_common(0), []);
+    assert_eq!(counter.most_common(10).len(), 5);
+
+    // Subtracting never goes below zero, and a key is forgotten when it gets there
+    assert_eq!(counter.subtract(&"b", 1), 2);
+    assert_eq!(counter.subtract(&"a", 10), 0);
+    assert!(!counter.contains_key(&"a"));
+    assert_eq!(counter.subtract(&"z", 1), 0);
+    assert_eq!(counter.total(), 5);
+
+    let other = vec!["b", "z"].into_iter().collect::<Counter<&str>>();
+    counter.merge(&other);
+    assert_eq!(counter.get(&"b"), 3);
+    assert_eq!(counter.get(&"z"), 1);
+    assert_eq!(counter.total(), 7);
+
+    assert_eq!(counter.remove(&"b"), 3);
+    assert_eq!(counter.remove(&"b"), 0);
+    assert_eq!(counter.total(), 4);
+    assert_eq!(counter.iter().map(|(_, n)| n).sum::<u64>(), counter.total());
+    assert_eq!(counter.keys().count(), counter.len());
+
+    counter.extend(vec!["c", "c"]);
+    assert_eq!(counter.add("c"), 4);
+    assert_eq!(counter.add_n("y", 5), 5);
+    assert_eq!(counter.most_common(1), [(&"y", 5)]);
+
+    counter.clear();
+    assert!(counter.is_empty());
+    assert_eq!(counter.total(), 0);
+}
+
+#[test]
+fn most_common() {
+    let mut counter = Counter::<u64>::new();
+    for i in 0..100_000 {
+        counter.add(i % 1000);
+    }
+    assert_eq!(counter.len(), 1000);
+    assert!(counter.iter().all(|(_, n)| n == 100));
+
+    // Give each key a distinct count, so that the order is fully determined
+    for i in 0..1000 {
+        counter.add_n(i, i * 7 % 1000);
+    }
+    let top = counter.most_common(10);
+    assert_eq!(top.len(), 10);
+    for (rank, (_, count)) in top.iter().enumerate() {
+        assert_eq!(*count, 100 + 999 - rank as u64);
+    }
+    let all = counter.most_common(usize::MAX);
+    assert_eq!(all.len(), 1000);
+    assert!(all.windows(2).all(|w| w[0].1 > w[1].1));
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use std::{cell::Cell, hash::BuildHasherDefault};
+
+use smash::CowHashMap;
+
+use common::CollidingHasher;
+
+thread_local! {
+    static CLONES: Cell<usize> = const { Cell::new(0) };
+}
+
+// A value that counts how many times it has been cloned on this thread
+#[derive(Debug, PartialEq)]
+struct Counted(u64);
+
+impl Clone for Counted {
+    fn clone(&self) -> Self {
+        CLONES.with(|c| c.set(c.get() + 1));
+        Counted(self.0)
+    }
+}
+
+fn clones() -> usize {
+    CLONES.with(|c| c.get())
+}
+
+#[test]
+fn snapshots() {
+    let mut map = CowHashMap::<u64, String>::new();
+    let mut reference = std::collections::HashMap::new();
+    let mut snapshots = Vec::new();
+    let mut state = 0x2545_F491_u64;
+    for i in 0..60_000 {
+        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        let key = (state >> 33) % 20_000;
+        if state >> 62 == 0 {
+            assert_eq!(map.remove(&key), reference.remove(&key));
+        } else {
+            assert_eq!(map.insert(key, i.to_string()), reference.insert(key, i.to_string()));
+        }
+        assert_eq!(map.len(), reference.len());
+        if i % 5000 == 0 {
+            snapshots.push((map.clone(), reference.clone()));
+        }
+    }
+    snapshots.push((map, reference));
+
+    // Changes to a map are never seen by its earlier clones
+    for (map, reference) in &snapshots {
+        assert_eq!(map.len(), reference.len());
+        assert_eq!(map.iter().count(), reference.len());
+        for (key, val) in reference {
+            assert_eq!(map.get(key), Some(val));
+        }
+    }
+}
+
+#[test]
+fn ptr_eq() {
+    let mut map = CowHashMap::<u64, u64>::new();
+    for i in 0..100 {
+        map.insert(i, i);
+    }
+    let mut clone = map.clone();
+    assert!(clone.ptr_eq(&map));
+
+    // Looking for something to change is not a change
+    assert_eq!(clone.remove(&1000), None);
+    assert!(clone.ptr_eq(&map));
+
+    *clone.get_mut(&5).unwrap() += 1;
+    assert!(!clone.ptr_eq(&map));
+    assert_eq!(map.get(&5), Some(&5));
+    assert_eq!(clone.get(&5), Some(&6));
+}
+
+#[test]
+fn writes_copy_one_shard() {
+    let mut map = CowHashMap::<u64, Counted>::new();
+    for i in 0..100_000 {
+        map.insert(i, Counted(i));
+    }
+    // Splitting a shard that is not shared moves its entries
+    assert_eq!(clones(), 0);
+
+    // Each change to a clone copies at most the one shard that it touches
+    let mut clone = map.clone();
+    clone.insert(100_000, Counted(0));
+    assert!(clones() <= 1024);
+    clone.remove(&7);
+    assert!(clones() <= 2048);
+    *clone.get_mut(&8).unwrap() = Counted(0);
+    assert!(clones() <= 3072);
+
+    assert_eq!(map.len(), 100_000);
+    assert_eq!(map.get(&7), Some(&Counted(7)));
+    assert_eq!(map.get(&100_000), None);
+    assert_eq!(clone.len(), 100_000);
+    assert_eq!(clone.get(&7), None);
+    assert_eq!(clone.get(&8), Some(&Counted(0)));
+
+    // Each shared shard is copied no more than once, however many times it is written to or split
+    let before = clones();
+    for i in 100_000..110_000 {
+        clone.insert(i, Counted(i));
+    }
+    assert!(clones() - before <= 100_000);
+    assert_eq!(clone.len(), 109_999);
+    assert_eq!(map.len(), 100_000);
+    for i in 0..110_000 {
+        assert_eq!(map.contains_key(&i), i < 100_000);
+    }
+}
+
+#[test]
+fn colliding() {
+    // Keys that share a hash can never be split apart, so they must stay in a single shard
+    let mut map = CowHashMap::<u64, u64, BuildHasherDefault<CollidingHasher>>::default();
+    for i in 0..5000 {
+        map.insert(i, i);
+    }
+    assert_eq!(map.len(), 5000);
+    let clone = map.clone();
+    for i in (0..5000).step_by(2) {
+        assert_eq!(map.remove(&i), Some(i));
+    }
+    for i in 0..5000 {
+        assert_eq!(map.get(&i), if i % 2 == 0 { None } else { Some(&i) });
+        assert_eq!(clone.get(&i), Some(&i));
+    }
+
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(map.iter().count(), 0);
+}
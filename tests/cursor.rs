@@ -0,0 +1,169 @@
+mod common;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    hash::BuildHasherDefault,
+};
+
+use smash::{
+    HashMap,
+    probe::{Linear, ProbeStrategy, Quadratic, RobinHood, Swiss},
+};
+
+use common::{Identity, TailHasher};
+
+// Slots 0, 1, 2, 14 and 15 of a 16-slot table hold 46, 31, 15, 14 and 30, which all belong at slot 14 or 15 and so
+// have wrapped around. Slots 5 and 6 hold 5 and 6, which keep the table from shrinking as entries are removed.
+fn wrapped() -> HashMap<u64, u64, Identity> {
+    let mut map = HashMap::with_capacity(16);
+    for key in [14, 15, 30, 31, 46, 5, 6] {
+        map.insert(key, key);
+    }
+    assert_eq!(map.keys().copied().collect::<Vec<_>>(), [46, 31, 15, 5, 6, 14, 30]);
+    map
+}
+
+#[test]
+fn remove_across_wrap() {
+    let mut map = wrapped();
+    let mut visited = Vec::new();
+    let mut cursor = map.cursor_mut();
+    while let Some(key) = cursor.key().copied() {
+        visited.push(key);
+        // Removing 14 shifts 30 back into its slot, and 46 (already visited) back around from slot 0 to slot 15
+        if key == 14 {
+            assert_eq!(cursor.remove_current(), Some((14, 14)));
+        } else {
+            cursor.move_next();
+        }
+    }
+    assert_eq!(visited, [46, 31, 15, 5, 6, 14, 30]);
+    assert!(!cursor.move_next());
+    assert_eq!(cursor.remove_current(), None);
+    drop(cursor);
+
+    assert_eq!(map.len(), 6);
+    assert_eq!(map.get(&14), None);
+    assert_eq!(map.get(&46), Some(&46));
+}
+
+#[test]
+fn remove_every_entry_across_wrap() {
+    let mut map = wrapped();
+    let mut removed = Vec::new();
+    let mut cursor = map.cursor_mut();
+    while let Some((key, _)) = cursor.remove_current() {
+        removed.push(key);
+    }
+    drop(cursor);
+
+    removed.sort();
+    assert_eq!(removed, [5, 6, 14, 15, 30, 31, 46]);
+    assert!(map.is_empty());
+}
+
+#[test]
+fn seek_after_wrap() {
+    let mut map = wrapped();
+    let mut cursor = map.cursor_mut();
+    while cursor.key() != Some(&14) {
+        cursor.move_next();
+    }
+    assert_eq!(cursor.remove_current(), Some((14, 14)));
+    assert_eq!(cursor.key(), Some(&30));
+
+    // Seeking to an entry that has been shifted back around is refused, since it was already visited
+    assert!(!cursor.seek(&46));
+    assert_eq!(cursor.key(), Some(&30));
+
+    // Seeking elsewhere keeps the end of the range, so 46 isn't visited again
+    assert!(cursor.seek(&6));
+    let mut visited = Vec::new();
+    while let Some(key) = cursor.key().copied() {
+        visited.push(key);
+        cursor.move_next();
+    }
+    assert_eq!(visited, [6, 30]);
+}
+
+#[test]
+fn seek() {
+    let mut map = HashMap::<u64, u64>::new();
+    for i in 0..20 {
+        map.insert(i, i);
+    }
+
+    let mut cursor = map.cursor_mut();
+    assert!(!cursor.seek(&100));
+    assert!(cursor.seek(&7));
+    assert_eq!(cursor.key(), Some(&7));
+    *cursor.value_mut().unwrap() = 70;
+    assert!(cursor.seek(&8));
+    assert_eq!(cursor.remove_current(), Some((8, 8)));
+    drop(cursor);
+
+    assert_eq!(map.get(&7), Some(&70));
+    assert_eq!(map.get(&8), None);
+    assert!(HashMap::<u64, u64>::new().cursor_mut().key().is_none());
+}
+
+// Random keeps, updates and removals, with probe sequences that wrap around, checked against a reference map
+fn random<P: ProbeStrategy>() {
+    let mut state = 0x2545_F491_u64;
+    let mut next = move || {
+        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        state >> 33
+    };
+
+    for round in 0..300 {
+        let len = next() % 60;
+        let mut map = HashMap::<u64, u64, BuildHasherDefault<TailHasher>, P>::new();
+        let mut reference = BTreeMap::new();
+        for i in 0..len {
+            map.insert(i, i);
+            reference.insert(i, i);
+        }
+
+        // Some rounds stop part way through
+        let stop = if round % 7 == 0 { len / 2 } else { len };
+        let mut visited = BTreeSet::new();
+        let mut cursor = map.cursor_mut();
+        while let Some(key) = cursor.key().copied() {
+            assert!(visited.insert(key), "visited {} twice", key);
+            if visited.len() as u64 > stop {
+                break;
+            }
+
+            match next() % 3 {
+                0 => {
+                    assert_eq!(cursor.remove_current(), reference.remove_entry(&key));
+                },
+                1 => {
+                    *cursor.value_mut().unwrap() += 1000;
+                    *reference.get_mut(&key).unwrap() += 1000;
+                    cursor.move_next();
+                },
+                _ => {
+                    cursor.move_next();
+                },
+            }
+        }
+        if stop == len {
+            assert_eq!(visited.len() as u64, len);
+        }
+        drop(cursor);
+
+        assert_eq!(map.len(), reference.len());
+        for (key, val) in &reference {
+            assert_eq!(map.get(key), Some(val));
+        }
+    }
+}
+
+#[test]
+fn strategies() {
+    random::<RobinHood>();
+    random::<Linear>();
+    random::<Quadratic>();
+    random::<Swiss>();
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use std::time::Duration;
+
+use smash::{ExpiringMap, ManualClock};
+
+use common::Identity;
+
+fn secs(secs: u64) -> Duration {
+    Duration::from_secs(secs)
+}
+
+fn map() -> (ExpiringMap<u32, String, ManualClock>, ManualClock) {
+    let clock = ManualClock::new();
+    (ExpiringMap::with_clock(clock.clone()), clock)
+}
+
+#[test]
+fn expiry_on_get() {
+    let (mut map, clock) = map();
+    map.insert(1, "one".to_string(), secs(10));
+    map.insert(2, "two".to_string(), secs(20));
+
+    clock.advance(secs(9));
+    assert_eq!(map.get(&1).map(String::as_str), Some("one"));
+    assert_eq!(map.ttl(&1), Some(secs(1)));
+
+    // Entries expire exactly at their deadline
+    clock.advance(secs(1));
+    assert_eq!(map.get(&1), None);
+    assert!(!map.contains_key(&1));
+    assert_eq!(map.ttl(&1), None);
+    assert_eq!(map.get(&2).map(String::as_str), Some("two"));
+    assert_eq!(map.iter().map(|(k, _)| *k).collect::<Vec<_>>(), [2]);
+
+    // Lookups that can't mutate leave the entry to be reclaimed later, while those that can reclaim it
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get_mut(&1), None);
+    assert_eq!(map.len(), 1);
+    clock.advance(secs(10));
+    assert_eq!(map.remove(&2), None);
+    assert!(map.is_empty());
+}
+
+#[test]
+fn expiry_on_insert() {
+    let (mut map, clock) = map();
+    assert_eq!(map.insert(1, "one".to_string(), secs(10)), None);
+    assert_eq!(map.insert(1, "uno".to_string(), secs(10)), Some("one".to_string()));
+
+    // An expired entry is replaced as if it were absent
+    clock.advance(secs(10));
+    assert_eq!(map.insert(1, "eins".to_string(), secs(10)), None);
+    assert_eq!(map.get(&1).map(String::as_str), Some("eins"));
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn refresh() {
+    let (mut map, clock) = map();
+    map.insert(1, "one".to_string(), secs(10));
+
+    // Inserting again gives the entry a new deadline, and the timer for the old one must not remove it
+    clock.advance(secs(5));
+    map.insert(1, "one".to_string(), secs(10));
+    clock.advance(secs(6));
+    assert_eq!(map.purge_expired(), 0);
+    assert_eq!(map.ttl(&1), Some(secs(4)));
+
+    clock.advance(secs(4));
+    assert_eq!(map.get(&1), None);
+    assert_eq!(map.purge_expired(), 1);
+    assert!(map.is_empty());
+
+    // A shorter time-to-live works too
+    map.insert(2, "two".to_string(), secs(100));
+    map.insert(2, "two".to_string(), secs(1));
+    clock.advance(secs(1));
+    assert_eq!(map.purge_expired(), 1);
+    assert!(map.is_empty());
+}
+
+#[test]
+fn purge_count() {
+    let (mut map, clock) = map();
+    for i in 0..1000 {
+        map.insert(i, i.to_string(), secs(1 + (i % 10) as u64));
+    }
+
+    clock.advance(Duration::from_millis(5500));
+    assert_eq!(map.iter().count(), 500);
+    assert_eq!(map.len(), 1000);
+    assert_eq!(map.purge_expired(), 500);
+    assert_eq!(map.len(), 500);
+    assert_eq!(map.purge_expired(), 0);
+
+    // Entries that were removed don't count
+    assert_eq!(map.remove(&9).as_deref(), Some("9"));
+    clock.advance(secs(5));
+    assert_eq!(map.purge_expired(), 499);
+    assert!(map.is_empty());
+
+    // Deadlines further away than one turn of the timer wheel
+    for i in 0..1000 {
+        map.insert(i, i.to_string(), secs(1000));
+    }
+    clock.advance(secs(999));
+    assert_eq!(map.purge_expired(), 0);
+    clock.advance(secs(1));
+    assert_eq!(map.purge_expired(), 1000);
+}
+
+#[test]
+fn clear() {
+    let (mut map, clock) = map();
+    map.insert(1, "one".to_string(), secs(1));
+    map.clear();
+    assert!(map.is_empty());
+
+    clock.advance(secs(1));
+    assert_eq!(map.purge_expired(), 0);
+}
+
+// Every timer is cancelled along with its entry, so none is left over to be counted when its deadline passes
+#[test]
+fn cancelled_timers() {
+    let (mut map, clock) = map();
+    map.insert(1, "one".to_string(), secs(1));
+    map.insert(1, "uno".to_string(), secs(100));
+    map.insert(2, "two".to_string(), secs(1));
+    assert_eq!(map.remove(&2).as_deref(), Some("two"));
+    clock.advance(secs(1));
+    assert_eq!(map.purge_expired(), 0);
+
+    assert_eq!(map.remove(&1).as_deref(), Some("uno"));
+    clock.advance(secs(100));
+    assert_eq!(map.purge_expired(), 0);
+    assert!(map.is_empty());
+}
+
+// Inserting reclaims the expired entries that its probe passes over, whatever their keys
+#[test]
+fn reclaim_on_probe() {
+    let clock = ManualClock::new();
+    let mut map = ExpiringMap::<u64, u64, ManualClock, Identity>::with_clock(clock.clone());
+
+    // All of these keys belong in the first slot of a small table
+    map.insert(0, 0, secs(1));
+    map.insert(16, 16, secs(1));
+    map.insert(32, 32, secs(100));
+    clock.advance(secs(1));
+    assert_eq!(map.len(), 3);
+
+    map.insert(48, 48, secs(100));
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get(&32), Some(&32));
+    assert_eq!(map.get(&48), Some(&48));
+    assert_eq!(map.purge_expired(), 0);
+}
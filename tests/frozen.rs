@@ -0,0 +1,78 @@
+mod common;
+
+use std::hash::BuildHasherDefault;
+
+use smash::HashMap;
+
+use common::{CollidingHasher, Identity};
+
+#[test]
+fn freeze() {
+    for len in [0, 1, 2, 3, 7, 100, 1000, 20_000] {
+        let mut map = HashMap::<u64, String>::new();
+        for i in 0..len {
+            map.insert(i * 7919, i.to_string());
+        }
+
+        let frozen = map.freeze();
+        assert_eq!(frozen.len(), len as usize);
+        assert_eq!(frozen.is_empty(), len == 0);
+        for i in 0..len {
+            assert_eq!(frozen.get(&(i * 7919)), Some(&i.to_string()));
+            assert!(!frozen.contains_key(&(i * 7919 + 1)));
+        }
+        assert_eq!(frozen.iter().count(), len as usize);
+    }
+}
+
+#[test]
+fn string_keys() {
+    let mut map = HashMap::<String, u32>::new();
+    for i in 0..5000 {
+        map.insert(format!("k{}", i), i);
+    }
+
+    let frozen = map.freeze();
+    for i in 0..5000 {
+        let key = format!("k{}", i);
+        assert_eq!(frozen.get_key_value(&key), Some((&key, &i)));
+    }
+    assert_eq!(frozen.get(&"nope".to_string()), None);
+
+    let mut keys = frozen.keys().cloned().collect::<Vec<_>>();
+    keys.sort();
+    let mut expected = (0..5000).map(|i| format!("k{}", i)).collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(keys, expected);
+    assert_eq!(frozen.values().sum::<u32>(), (0..5000).sum());
+}
+
+#[test]
+fn colliding_hasher() {
+    let mut map = HashMap::<u32, u32, BuildHasherDefault<CollidingHasher>>::new();
+    for i in 0..100 {
+        map.insert(i, i * 2);
+    }
+
+    let frozen = map.freeze();
+    assert_eq!(frozen.len(), 100);
+    for i in 0..100 {
+        assert_eq!(frozen.get(&i), Some(&(i * 2)));
+    }
+    assert_eq!(frozen.get(&100), None);
+}
+
+// Consecutive keys hashed to themselves, which ignore the seed and would all share a bucket unless the hashes are mixed
+#[test]
+fn identity_hasher() {
+    let mut map = HashMap::<u64, u64, Identity>::default();
+    for i in 0..20_000 {
+        map.insert(i, i + 1);
+    }
+
+    let frozen = map.freeze();
+    for i in 0..20_000 {
+        assert_eq!(frozen.get(&i), Some(&(i + 1)));
+    }
+    assert_eq!(frozen.get(&20_000), None);
+}
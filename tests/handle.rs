@@ -0,0 +1,115 @@
+mod common;
+
+use smash::HashMap;
+
+use common::Identity;
+
+type Map = HashMap<u64, u64, Identity>;
+
+#[test]
+fn access_by_handle() {
+    let mut map = Map::with_capacity(16);
+    let (handle, old) = map.insert_with_handle(1, 10);
+    assert_eq!(old, None);
+    assert_eq!(map.get_by_handle(handle), Some(&10));
+    assert_eq!(map.get_key_value_by_handle(handle), Some((&1, &10)));
+    *map.get_mut_by_handle(handle).unwrap() += 1;
+    assert_eq!(map.get(&1), Some(&11));
+
+    // Replacing the value keeps the entry where it is
+    let (again, old) = map.insert_with_handle(1, 12);
+    assert_eq!(old, Some(11));
+    assert_eq!(again, handle);
+    assert_eq!(map.handle(&1), Some(handle));
+    assert_eq!(map.handle(&2), None);
+
+    assert_eq!(map.remove_by_handle(handle), Some((1, 12)));
+    assert!(map.is_empty());
+}
+
+#[test]
+fn handles_outlive_other_entries() {
+    let mut map = Map::with_capacity(16);
+    let handles = (0..8).map(|i| map.insert_with_handle(i * 2, i).0).collect::<Vec<_>>();
+
+    // None of these keys share a probe sequence, so removing some (but not enough to shrink the map) moves nothing
+    for i in [0, 3, 6] {
+        assert_eq!(map.remove(&(i * 2)), Some(i));
+    }
+    for i in [1, 2, 4, 5, 7] {
+        assert_eq!(map.get_by_handle(handles[i as usize]), Some(&i));
+    }
+}
+
+#[test]
+fn stale_handles() {
+    // Enough other entries that removals never shrink the map, which would invalidate every handle at once
+    let mut map = Map::with_capacity(16);
+    for i in 8..14 {
+        map.insert(i, i);
+    }
+    let capacity = map.capacity();
+    let handle = map.insert_with_handle(3, 3).0;
+    map.remove(&3);
+    assert_eq!(map.capacity(), capacity);
+    assert!(!map.is_valid_handle(handle));
+    assert_eq!(map.get_by_handle(handle), None);
+    assert_eq!(map.remove_by_handle(handle), None);
+
+    // A different key in the same slot
+    map.insert(19, 19);
+    assert_eq!(map.capacity(), capacity);
+    assert!(!map.is_valid_handle(handle));
+    assert_eq!(map.get_mut_by_handle(handle), None);
+
+    // Displaced by a key that has probed further
+    let mut map = Map::with_capacity(16);
+    for i in 8..14 {
+        map.insert(i, i);
+    }
+    let capacity = map.capacity();
+    let displaced = map.insert_with_handle(4, 4).0;
+    map.insert(3, 3);
+    map.insert(19, 19);
+    assert_eq!(map.capacity(), capacity);
+    assert_eq!(map.get_by_handle(displaced), None);
+    assert_eq!(map.get(&4), Some(&4));
+
+    // Shifted back by a removal
+    let shifted = map.handle(&4).unwrap();
+    map.remove(&19);
+    assert_eq!(map.capacity(), capacity);
+    assert_eq!(map.get_by_handle(shifted), None);
+    assert_eq!(map.get(&4), Some(&4));
+
+    // Moved by a resize
+    let handle = map.handle(&3).unwrap();
+    map.reserve(100);
+    assert_eq!(map.get_by_handle(handle), None);
+    assert_eq!(map.get(&3), Some(&3));
+}
+
+#[test]
+fn handles_from_other_maps() {
+    let mut a = Map::with_capacity(16);
+    let mut b = Map::with_capacity(16);
+    let handle = a.insert_with_handle(5, 5).0;
+    assert_eq!(b.get_by_handle(handle), None);
+
+    // Even when the other map has an entry in the same slot
+    b.insert(5, 50);
+    assert!(!b.is_valid_handle(handle));
+    assert_eq!(b.get_by_handle(handle), None);
+    assert_eq!(b.remove_by_handle(handle), None);
+    assert_eq!(b.len(), 1);
+
+    let mut clone = a.clone();
+    assert_eq!(clone.get_by_handle(handle), None);
+    assert_eq!(clone.remove_by_handle(handle), None);
+    assert_eq!(clone.len(), 1);
+    assert_eq!(a.get_by_handle(handle), Some(&5));
+
+    b.clone_from(&a);
+    assert_eq!(b.get_by_handle(handle), None);
+    assert_eq!(b.get(&5), Some(&5));
+}
@@ -0,0 +1,77 @@
+use std::{rc::Rc, sync::Arc, thread};
+
+use smash::{HashCons, SyncHashCons};
+
+#[derive(Hash, PartialEq, Eq, Debug)]
+enum Ty {
+    Int,
+    Fn(Rc<Ty>, Rc<Ty>),
+}
+
+#[test]
+fn sharing() {
+    let mut cons = HashCons::<Ty>::new();
+    assert!(cons.is_empty());
+    let a = cons.intern(Ty::Int);
+    let b = cons.intern(Ty::Int);
+    assert!(Rc::ptr_eq(&a, &b));
+
+    // Structurally equal values are shared even when built from different pointers
+    let f = cons.intern(Ty::Fn(a.clone(), b.clone()));
+    let g = cons.intern(Ty::Fn(b.clone(), a.clone()));
+    assert!(Rc::ptr_eq(&f, &g));
+    assert!(Rc::ptr_eq(&cons.get(&Ty::Fn(a.clone(), a.clone())).unwrap(), &f));
+    assert_eq!(cons.len(), 2);
+    assert_eq!(cons.live_count(), 2);
+
+    // Dead values are no longer found, but keep their entries until they are collected
+    drop((f, g));
+    assert_eq!(cons.live_count(), 1);
+    assert_eq!(cons.len(), 2);
+    assert!(cons.get(&Ty::Fn(a.clone(), a.clone())).is_none());
+    assert_eq!(cons.collect_garbage(), 1);
+    assert_eq!(cons.len(), 1);
+    assert_eq!(cons.collect_garbage(), 0);
+
+    // A value that died is created afresh
+    let f = cons.intern(Ty::Fn(a.clone(), a.clone()));
+    assert_eq!(Rc::strong_count(&f), 1);
+    assert!(Rc::ptr_eq(&cons.intern(Ty::Int), &a));
+}
+
+#[test]
+fn dead_entries_are_reused() {
+    let mut cons = HashCons::<u64>::new();
+    for _ in 0..10 {
+        let vals = (0..1000).map(|i| cons.intern(i)).collect::<Vec<_>>();
+        assert_eq!(cons.live_count(), 1000);
+        drop(vals);
+        assert_eq!(cons.live_count(), 0);
+    }
+    // Rounds of short-lived values make room by collecting the last round rather than growing
+    assert!(cons.len() <= 2048);
+}
+
+#[test]
+fn sync() {
+    let cons = Arc::new(SyncHashCons::<String>::new());
+    let threads = (0..4)
+        .map(|_| {
+            let cons = cons.clone();
+            thread::spawn(move || (0..100).map(|i| cons.intern(i.to_string())).collect::<Vec<_>>())
+        })
+        .collect::<Vec<_>>();
+    let vals = threads.into_iter().map(|t| t.join().unwrap()).collect::<Vec<_>>();
+    for i in 0..100 {
+        assert!(vals.iter().all(|v| Arc::ptr_eq(&v[i], &vals[0][i])));
+        assert!(Arc::ptr_eq(&cons.get(&i.to_string()).unwrap(), &vals[0][i]));
+    }
+    assert_eq!(cons.len(), 100);
+    assert_eq!(cons.live_count(), 100);
+
+    drop(vals);
+    assert_eq!(cons.live_count(), 0);
+    assert!(cons.get(&"0".to_string()).is_none());
+    assert_eq!(cons.collect_garbage(), 100);
+    assert!(cons.is_empty());
+}
@@ -0,0 +1,145 @@
+use smash::{IndexedCollection, MultiIndex, UniqueIndex, UniqueViolation};
+
+#[derive(Clone, Debug, PartialEq)]
+struct User {
+    id: u32,
+    email: String,
+    tenant: u32,
+    name: String,
+}
+
+fn user(id: u32, email: &str, tenant: u32, name: &str) -> User {
+    User { id, email: email.to_string(), tenant, name: name.to_string() }
+}
+
+#[test]
+fn indexes() {
+    let mut users = IndexedCollection::<User>::new();
+    let by_id = users.add_unique_index(|u: &User| u.id).unwrap();
+    let by_email = users.add_unique_index(|u: &User| u.email.clone()).unwrap();
+    let by_name = users.add_index(|u: &User| (u.tenant, u.name.clone()));
+    let by_tenant = users.add_index(|u: &User| u.tenant);
+
+    let a = users.insert(user(1, "a@x", 1, "ann")).unwrap();
+    let b = users.insert(user(2, "b@x", 1, "bob")).unwrap();
+    let c = users.insert(user(3, "c@x", 2, "ann")).unwrap();
+    assert_eq!(users.len(), 3);
+
+    // A record that clashes with another in any unique index is handed back untouched
+    let err = users.insert(user(4, "a@x", 3, "zed")).unwrap_err();
+    assert_eq!(err, UniqueViolation { record: user(4, "a@x", 3, "zed"), existing: a });
+    assert_eq!(users.len(), 3);
+    assert!(users.get_by(by_id, &4).is_none());
+    assert!(users.get_all_by(by_tenant, &3).next().is_none());
+
+    assert_eq!(users.get_by(by_email, &"b@x".to_string()).map(|(id, _)| id), Some(b));
+    assert_eq!(users.get_all_by(by_tenant, &1).map(|(id, _)| id).collect::<Vec<_>>(), [a, b]);
+    assert_eq!(users.get_all_by(by_name, &(2, "ann".to_string())).map(|(id, _)| id).collect::<Vec<_>>(), [c]);
+    assert_eq!(users.get(c), Some(&user(3, "c@x", 2, "ann")));
+    assert!(users.contains(c));
+
+    // Existing records that already clash prevent a unique index from being added
+    assert_eq!(users.add_unique_index(|u: &User| u.tenant).err(), Some(b));
+}
+
+#[test]
+fn updates() {
+    let mut users = IndexedCollection::<User>::new();
+    let by_id = users.add_unique_index(|u: &User| u.id).unwrap();
+    let by_email = users.add_unique_index(|u: &User| u.email.clone()).unwrap();
+    let by_tenant = users.add_index(|u: &User| u.tenant);
+    let a = users.insert(user(1, "a@x", 1, "ann")).unwrap();
+    let b = users.insert(user(2, "b@x", 1, "bob")).unwrap();
+    users.insert(user(3, "c@x", 2, "cat")).unwrap();
+
+    // A failed update leaves the record and every index as they were
+    let err = users.update(b, |u| {
+        u.email = "c@x".to_string();
+        u.tenant = 9;
+    });
+    assert!(err.is_err());
+    assert_eq!(users.get(b).unwrap().tenant, 1);
+    assert_eq!(users.get_all_by(by_tenant, &9).count(), 0);
+    assert_eq!(users.get_by(by_email, &"b@x".to_string()).map(|(id, _)| id), Some(b));
+
+    users.update(b, |u| {
+        u.email = "bb@x".to_string();
+        u.tenant = 2;
+    }).unwrap();
+    assert!(users.get_by(by_email, &"b@x".to_string()).is_none());
+    assert_eq!(users.get_by(by_email, &"bb@x".to_string()).map(|(id, _)| id), Some(b));
+    assert_eq!(users.get_all_by(by_tenant, &2).count(), 2);
+    assert_eq!(users.get_all_by(by_tenant, &1).count(), 1);
+
+    // A record may keep its own unique keys
+    users.update(a, |u| u.name = "anna".to_string()).unwrap();
+    assert_eq!(users.replace(a, user(1, "a@x", 1, "annie")).unwrap().name, "anna");
+    assert!(users.replace(a, user(1, "bb@x", 1, "annie")).is_err());
+
+    assert_eq!(users.remove_by(by_id, &1).unwrap().name, "annie");
+    assert_eq!(users.remove_by(by_id, &1), None);
+    assert!(!users.contains(a));
+    assert_eq!(users.get_all_by(by_tenant, &1).count(), 0);
+    assert!(users.get_by(by_email, &"a@x".to_string()).is_none());
+
+    // Ids are reused once their record is gone
+    assert_eq!(users.insert(user(1, "a@x", 1, "again")).unwrap(), a);
+    assert_eq!(users.remove(b).map(|u| u.id), Some(2));
+    assert_eq!(users.remove(b), None);
+    assert_eq!(users.len(), 2);
+}
+
+// Random insertions and removals, after which every index must agree with the records
+#[test]
+fn random() {
+    let mut users = IndexedCollection::<User>::new();
+    let by_id = users.add_unique_index(|u: &User| u.id).unwrap();
+    let by_tenant = users.add_index(|u: &User| u.tenant);
+
+    let mut ids = Vec::new();
+    let mut state = 0x2545_F491_u64;
+    for _ in 0..20_000 {
+        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        let key = ((state >> 33) % 500) as u32;
+        if state >> 62 == 0 && !ids.is_empty() {
+            let id = ids.swap_remove((state >> 20) as usize % ids.len());
+            users.remove(id).unwrap();
+        } else {
+            match users.insert(user(key, &format!("{}@y", key), key % 7, "n")) {
+                Ok(id) => ids.push(id),
+                Err(err) => assert_eq!(users.get(err.existing).unwrap().id, key),
+            }
+        }
+        assert_eq!(users.len(), ids.len());
+    }
+
+    for tenant in 0..7 {
+        let expected = users.iter().filter(|(_, u)| u.tenant == tenant).count();
+        assert_eq!(users.get_all_by(by_tenant, &tenant).count(), expected);
+        assert!(users.get_all_by(by_tenant, &tenant).all(|(_, u)| u.tenant == tenant));
+    }
+    for (id, u) in users.iter() {
+        assert_eq!(users.get_by(by_id, &u.id).map(|(id, _)| id), Some(id));
+    }
+}
+
+// An index of the same shape from another collection would otherwise query this collection's index in its place
+#[test]
+#[should_panic(expected = "Index does not belong to this collection")]
+fn foreign_unique_index() {
+    let mut users = IndexedCollection::<User>::new();
+    let mut others = IndexedCollection::<User>::new();
+    users.add_unique_index(|u: &User| u.id).unwrap();
+    let by_id: UniqueIndex<u32> = others.add_unique_index(|u: &User| u.tenant).unwrap();
+    users.insert(user(1, "a@x", 1, "ann")).unwrap();
+    users.get_by(by_id, &1);
+}
+
+#[test]
+#[should_panic(expected = "Index does not belong to this collection")]
+fn foreign_multi_index() {
+    let users = IndexedCollection::<User>::new();
+    let mut others = IndexedCollection::<User>::new();
+    let by_tenant: MultiIndex<u32> = others.add_index(|u: &User| u.tenant);
+    users.get_all_by(by_tenant, &1).count();
+}
@@ -0,0 +1,86 @@
+use std::{sync::Arc, thread};
+
+use smash::{Interner, Symbol, SyncInterner};
+
+#[test]
+fn intern() {
+    let mut interner = Interner::<fxhash::FxBuildHasher>::new();
+    assert!(interner.is_empty());
+    let words = (0..20_000).map(|n| format!("word{}", n % 5000)).collect::<Vec<_>>();
+    let syms = words.iter().map(|word| interner.intern(word)).collect::<Vec<_>>();
+    assert_eq!(interner.len(), 5000);
+    for (word, sym) in words.iter().zip(&syms) {
+        assert_eq!(interner.resolve(*sym), word);
+        assert_eq!(interner.get(word), Some(*sym));
+    }
+    assert_eq!(interner.get("nope"), None);
+
+    // Symbols are handed out in order, and iteration follows it
+    assert_eq!(syms[1].as_u32(), 1);
+    assert_eq!(syms[5000], syms[0]);
+    for (n, (sym, word)) in interner.iter().enumerate() {
+        assert_eq!(sym.as_u32(), n as u32);
+        assert_eq!(word, words[n]);
+    }
+
+    // Strings larger than a chunk, and the empty string
+    let big = "x".repeat(100_000);
+    let sym = interner.intern(&big);
+    assert_eq!(interner.resolve(sym), big);
+    assert_eq!(interner.intern(""), interner.intern(""));
+    assert_eq!(interner.resolve(interner.get("").unwrap()), "");
+
+    // Strings interned earlier stay where they are as more are added
+    let first = interner.resolve(syms[0]) as *const str;
+    for n in 0..10_000 {
+        interner.intern(&format!("more{}", n));
+    }
+    assert_eq!(interner.resolve(syms[0]) as *const str, first);
+    assert_eq!(interner.try_resolve(syms[0]), Some("word0"));
+
+    let other = Interner::<fxhash::FxBuildHasher>::new();
+    assert_eq!(other.try_resolve(syms[0]), None);
+}
+
+#[test]
+#[should_panic]
+fn resolve_foreign_symbol() {
+    let mut interner = Interner::<fxhash::FxBuildHasher>::new();
+    let sym = interner.intern("a");
+    Interner::<fxhash::FxBuildHasher>::new().resolve(sym);
+}
+
+#[test]
+fn sync() {
+    let mut interner = Interner::<fxhash::FxBuildHasher>::new();
+    let shared = interner.intern("shared");
+    let interner = Arc::new(SyncInterner::from(interner));
+
+    let threads = (0..4)
+        .map(|_| {
+            let interner = interner.clone();
+            thread::spawn(move || {
+                (0..1000)
+                    .map(|n| {
+                        let word = format!("t{}", n);
+                        let sym = interner.intern(&word);
+                        assert_eq!(interner.resolve(sym), word);
+                        assert_eq!(interner.intern("shared"), shared);
+                        sym
+                    })
+                    .collect::<Vec<Symbol>>()
+            })
+        })
+        .collect::<Vec<_>>();
+    let syms = threads.into_iter().map(|t| t.join().unwrap()).collect::<Vec<_>>();
+
+    // Every thread was given the same symbols for the same strings
+    assert!(syms.windows(2).all(|w| w[0] == w[1]));
+    assert_eq!(interner.len(), 1001);
+    assert_eq!(interner.get("t999"), Some(syms[0][999]));
+    assert_eq!(interner.try_resolve(syms[0][0]), Some("t0"));
+
+    let interner = Arc::try_unwrap(interner).ok().unwrap().into_inner();
+    assert_eq!(interner.len(), 1001);
+    assert_eq!(interner.resolve(shared), "shared");
+}
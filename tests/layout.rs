@@ -0,0 +1,130 @@
+use std::{collections::BTreeMap, rc::Rc};
+
+use fxhash::FxBuildHasher;
+use smash::{
+    HashMap,
+    layout::{AoS, CacheLine, Layout, SoA},
+    probe::{Hopscotch, Linear, ProbeStrategy, Quadratic, RobinHood, Swiss},
+};
+
+// Mixed operations, checked against the standard library's map
+fn random<P: ProbeStrategy, L: Layout>() {
+    let mut map = HashMap::<u32, u64, FxBuildHasher, P, L>::new();
+    let mut reference = std::collections::HashMap::new();
+    let mut state = 0x2545_F491_u64;
+    for _ in 0..50_000 {
+        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        let key = ((state >> 33) % 2000) as u32;
+        match state >> 61 {
+            0 | 1 => assert_eq!(map.remove(&key), reference.remove(&key)),
+            2 => {
+                if let Some(val) = map.get_mut(&key) {
+                    *val += 1;
+                }
+                if let Some(val) = reference.get_mut(&key) {
+                    *val += 1;
+                }
+            },
+            _ => assert_eq!(map.insert(key, state >> 32), reference.insert(key, state >> 32)),
+        }
+        assert_eq!(map.len(), reference.len());
+    }
+    for key in 0..2000 {
+        assert_eq!(map.get(&key), reference.get(&key));
+    }
+
+    // Every iterator visits each entry exactly once
+    let entries = map.iter().map(|(k, v)| (*k, *v)).collect::<BTreeMap<_, _>>();
+    assert_eq!(entries, reference.iter().map(|(k, v)| (*k, *v)).collect());
+    assert_eq!(map.keys().count(), reference.len());
+    assert_eq!(map.values().sum::<u64>(), reference.values().sum::<u64>());
+    for val in map.values_mut() {
+        *val = 0;
+    }
+    for (key, val) in map.iter_mut() {
+        *val += *key as u64;
+    }
+    assert!(map.iter().all(|(k, v)| *k as u64 == *v));
+
+    map.retain(|k, _| k % 3 == 0);
+    assert_eq!(map.len(), reference.keys().filter(|k| *k % 3 == 0).count());
+    map.shrink_to_fit();
+    assert!(map.keys().all(|k| k % 3 == 0 && map.get(k) == Some(&(*k as u64))));
+
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(map.iter().count(), 0);
+}
+
+#[test]
+fn layouts() {
+    random::<RobinHood, SoA>();
+    random::<RobinHood, AoS>();
+    random::<RobinHood, CacheLine>();
+}
+
+#[test]
+fn layouts_and_strategies() {
+    random::<Linear, AoS>();
+    random::<Quadratic, CacheLine>();
+    random::<Hopscotch, SoA>();
+    random::<Swiss, AoS>();
+    random::<Swiss, CacheLine>();
+}
+
+// Keys and values of awkward sizes, which do not fill a cache line evenly
+fn sizes<L: Layout>() {
+    let mut map = HashMap::<[u8; 3], (u64, u8), FxBuildHasher, RobinHood, L>::new();
+    for i in 0..3000u32 {
+        let [a, b, c, _] = i.to_le_bytes();
+        map.insert([a, b, c], (i as u64, a));
+    }
+    for i in 0..3000u32 {
+        let [a, b, c, _] = i.to_le_bytes();
+        assert_eq!(map.get(&[a, b, c]), Some(&(i as u64, a)));
+    }
+
+    let mut set = HashMap::<u16, (), FxBuildHasher, RobinHood, L>::new();
+    for i in 0..1000 {
+        set.insert(i * 7, ());
+    }
+    assert_eq!(set.len(), 1000);
+    assert!(set.contains_key(&693));
+    assert!(!set.contains_key(&694));
+}
+
+#[test]
+fn odd_sizes() {
+    sizes::<SoA>();
+    sizes::<AoS>();
+    sizes::<CacheLine>();
+}
+
+fn drops<L: Layout>() {
+    let rc = Rc::new(());
+    let mut map = HashMap::<String, Rc<()>, FxBuildHasher, RobinHood, L>::new();
+    for i in 0..500 {
+        map.insert(i.to_string(), rc.clone());
+    }
+    map.insert("7".to_string(), rc.clone());
+    assert_eq!(Rc::strong_count(&rc), 501);
+    for i in 0..100 {
+        assert!(map.remove(&i.to_string()).is_some());
+    }
+    assert_eq!(Rc::strong_count(&rc), 401);
+
+    let clone = map.clone();
+    assert_eq!(Rc::strong_count(&rc), 801);
+    drop(clone);
+    map.retain(|k, _| k.len() == 3);
+    assert_eq!(Rc::strong_count(&rc), 401);
+    drop(map);
+    assert_eq!(Rc::strong_count(&rc), 1);
+}
+
+#[test]
+fn layout_drops() {
+    drops::<SoA>();
+    drops::<AoS>();
+    drops::<CacheLine>();
+}
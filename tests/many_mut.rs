@@ -0,0 +1,54 @@
+use fxhash::FxBuildHasher;
+use smash::{
+    HashMap,
+    layout::{AoS, CacheLine, Layout, SoA},
+    probe::{Hopscotch, ProbeStrategy, RobinHood, Swiss},
+};
+
+fn many_mut<P: ProbeStrategy, L: Layout>() {
+    let mut stock = HashMap::<String, u32, FxBuildHasher, P, L>::new();
+    for i in 0..100 {
+        stock.insert(format!("slot{}", i), 10);
+    }
+    let [a, b] = stock.get_many_mut([&"slot3".to_string(), &"slot40".to_string()]).unwrap();
+    *a -= 4;
+    *b += 4;
+    assert_eq!(stock.get(&"slot3".to_string()), Some(&6));
+    assert_eq!(stock.get(&"slot40".to_string()), Some(&14));
+
+    // Every reference points at its own key's value, in the order the keys were given
+    let keys = (0..100).rev().map(|i| format!("slot{}", i)).collect::<Vec<_>>();
+    let mut vals: [&mut u32; 3] = stock.get_many_mut([&keys[0], &keys[50], &keys[99]]).unwrap();
+    for (val, n) in vals.iter_mut().zip([99, 49, 0]) {
+        **val = n;
+    }
+    for n in [99, 49, 0] {
+        assert_eq!(stock.get(&format!("slot{}", n)), Some(&n));
+    }
+
+    // A missing key or the same key twice gives nothing, and leaves the map untouched
+    assert!(stock.get_many_mut([&"slot1".to_string(), &"none".to_string()]).is_none());
+    assert!(stock.get_many_mut([&"slot1".to_string(), &"slot2".to_string(), &"slot1".to_string()]).is_none());
+    assert!(stock.get_many_mut::<0>([]).is_some());
+    assert_eq!(stock.len(), 100);
+    assert_eq!(stock.get(&"slot1".to_string()), Some(&10));
+
+    let [x, y] = unsafe { stock.get_many_unchecked_mut([&"slot7".to_string(), &"slot8".to_string()]) }.unwrap();
+    std::mem::swap(x, y);
+    *x = 1;
+    assert_eq!(stock.get(&"slot7".to_string()), Some(&1));
+    assert!(unsafe { stock.get_many_unchecked_mut([&"none".to_string()]) }.is_none());
+}
+
+#[test]
+fn layouts() {
+    many_mut::<RobinHood, SoA>();
+    many_mut::<RobinHood, AoS>();
+    many_mut::<RobinHood, CacheLine>();
+}
+
+#[test]
+fn strategies() {
+    many_mut::<Hopscotch, SoA>();
+    many_mut::<Swiss, AoS>();
+}
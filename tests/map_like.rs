@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+
+use smash::{
+    MapLike,
+    layout::{AoS, CacheLine},
+    probe::{Hopscotch, Linear, Quadratic, Swiss},
+};
+
+// Checks that a map behaves like every other `MapLike`, using a `BTreeMap` as the reference
+fn conformance<M: MapLike<u32, String> + Default>() {
+    let mut map = M::default();
+    assert!(map.is_empty());
+    assert_eq!(map.len(), 0);
+    assert_eq!(map.get(&0), None);
+    assert_eq!(map.remove(&0), None);
+    assert_eq!(map.iter().count(), 0);
+
+    assert_eq!(map.insert(1, "one".to_string()), None);
+    assert_eq!(map.insert(1, "uno".to_string()), Some("one".to_string()));
+    assert_eq!(map.get(&1).map(String::as_str), Some("uno"));
+    assert_eq!(map.len(), 1);
+
+    // Present keys keep their value and `f` is never called
+    assert_eq!(map.get_or_insert_with(1, || unreachable!()), "uno");
+    map.get_or_insert_with(2, || "two".to_string()).push('!');
+    assert_eq!(map.get(&2).map(String::as_str), Some("two!"));
+
+    assert_eq!(map.remove(&1), Some("uno".to_string()));
+    assert_eq!(map.remove(&1), None);
+    assert_eq!(map.get(&1), None);
+    assert_eq!(map.len(), 1);
+
+    // Mixed operations, driven by a simple LCG so that every implementation sees the same sequence
+    let mut reference = BTreeMap::new();
+    reference.insert(2, "two!".to_string());
+    let mut state = 0x2545_F491_u32;
+    for _ in 0..20_000 {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        let key = (state >> 8) % 1024;
+        match state >> 30 {
+            0 | 1 => assert_eq!(map.insert(key, key.to_string()), reference.insert(key, key.to_string())),
+            2 => assert_eq!(map.remove(&key), reference.remove(&key)),
+            _ => {
+                let val = map.get_or_insert_with(key, || "new".to_string());
+                assert_eq!(val, reference.entry(key).or_insert_with(|| "new".to_string()));
+            },
+        }
+        assert_eq!(map.len(), reference.len());
+    }
+
+    for (key, val) in &reference {
+        assert_eq!(map.get(key), Some(val));
+    }
+    let mut entries = map.iter().map(|(k, v)| (*k, v.clone())).collect::<Vec<_>>();
+    entries.sort();
+    assert_eq!(entries, reference.into_iter().collect::<Vec<_>>());
+}
+
+type SmashMap<P = smash::probe::RobinHood, L = smash::layout::SoA> =
+    smash::HashMap<u32, String, fxhash::FxBuildHasher, P, L>;
+
+#[test]
+fn smash() {
+    conformance::<smash::HashMap<u32, String>>();
+}
+
+#[test]
+fn smash_strategies() {
+    conformance::<SmashMap<Linear>>();
+    conformance::<SmashMap<Quadratic>>();
+    conformance::<SmashMap<Hopscotch>>();
+    conformance::<SmashMap<Swiss>>();
+}
+
+#[test]
+fn smash_layouts() {
+    conformance::<SmashMap<smash::probe::RobinHood, AoS>>();
+    conformance::<SmashMap<smash::probe::RobinHood, CacheLine>>();
+}
+
+#[test]
+fn std() {
+    conformance::<std::collections::HashMap<u32, String>>();
+}
+
+#[cfg(feature = "hashbrown")]
+#[test]
+fn hashbrown() {
+    conformance::<hashbrown::HashMap<u32, String>>();
+}
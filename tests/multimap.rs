@@ -0,0 +1,85 @@
+use smash::MultiMap;
+
+#[test]
+fn groups() {
+    let mut map = MultiMap::<u32, String>::new();
+    assert!(map.is_empty());
+    for i in 0..100 {
+        for j in 0..i % 4 {
+            map.insert(i, format!("{}-{}", i, j));
+        }
+    }
+    assert_eq!(map.len_keys(), 75);
+    assert_eq!(map.len_values(), 150);
+    assert_eq!(map.get(&3).map(String::as_str), Some("3-0"));
+    assert_eq!(map.get_all(&3), ["3-0", "3-1", "3-2"]);
+    assert_eq!(map.get_all(&4), [] as [String; 0]);
+    assert_eq!(map.get(&4), None);
+
+    map.get_all_mut(&2)[1].push('!');
+    assert_eq!(map.get_all(&2), ["2-0", "2-1!"]);
+    assert_eq!(map.get_all_mut(&4), [] as [String; 0]);
+
+    // Values are removed from the middle of a group, and a key goes once its last value does
+    assert_eq!(map.remove_one(&3, &"3-1".to_string()), Some("3-1".to_string()));
+    assert_eq!(map.get_all(&3), ["3-0", "3-2"]);
+    assert_eq!(map.remove_one(&3, &"3-1".to_string()), None);
+    assert_eq!(map.remove_one(&1, &"1-0".to_string()), Some("1-0".to_string()));
+    assert!(!map.contains_key(&1));
+    assert_eq!(map.len_keys(), 74);
+    assert_eq!(map.len_values(), 148);
+
+    assert_eq!(map.remove_all(&2), ["2-0", "2-1!"]);
+    assert_eq!(map.remove_all(&2), [] as [String; 0]);
+    assert_eq!(map.len_values(), 146);
+
+    map.retain_values(|_, val| !val.ends_with("-0"));
+    assert_eq!(map.len_values(), 73);
+    assert_eq!(map.len_keys(), 49);
+    assert_eq!(map.iter().map(|(_, vals)| vals.len()).sum::<usize>(), map.len_values());
+    assert_eq!(map.keys().count(), map.len_keys());
+    assert_eq!(map.get_all(&3), ["3-2"]);
+
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(map.len_values(), 0);
+}
+
+// Mixed operations, checked against a standard library map of vectors
+#[test]
+fn random() {
+    let mut map = MultiMap::<u64, u64>::new();
+    let mut reference = std::collections::HashMap::<u64, Vec<u64>>::new();
+    let mut state = 0x2545_F491_u64;
+    for _ in 0..20_000 {
+        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        let key = (state >> 33) % 200;
+        let val = (state >> 20) % 4;
+        match state >> 61 {
+            0 => {
+                let expected = reference.get_mut(&key).and_then(|vals| {
+                    let pos = vals.iter().position(|v| *v == val)?;
+                    Some(vals.remove(pos))
+                });
+                if reference.get(&key).is_some_and(Vec::is_empty) {
+                    reference.remove(&key);
+                }
+                assert_eq!(map.remove_one(&key, &val), expected);
+            },
+            1 => assert_eq!(map.remove_all(&key), reference.remove(&key).unwrap_or_default()),
+            _ => {
+                map.insert(key, val);
+                reference.entry(key).or_default().push(val);
+            },
+        }
+        assert_eq!(map.len_keys(), reference.len());
+        assert_eq!(map.len_values(), reference.values().map(Vec::len).sum::<usize>());
+    }
+
+    for key in 0..200 {
+        assert_eq!(map.get_all(&key), reference.get(&key).map_or(&[][..], Vec::as_slice));
+    }
+    for (key, vals) in map.iter() {
+        assert_eq!(reference[key], vals);
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    cell::Cell,
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+use fxhash::FxBuildHasher;
+use smash::{
+    HashMap,
+    layout::{AoS, CacheLine, Layout, SoA},
+    probe::RobinHood,
+};
+
+type Map<K, V, L> = HashMap<K, V, FxBuildHasher, RobinHood, L>;
+
+// Keys without a niche use every bit pattern, so none of them can stand for an empty slot
+fn no_niche<L: Layout>() {
+    let mut map = Map::<u64, u64, L>::new();
+    let keys = [0, 1, u64::MAX, u64::MAX - 1, 1 << 63];
+    for key in keys {
+        assert_eq!(map.insert(key, !key), None);
+    }
+    for key in keys {
+        assert_eq!(map.get(&key), Some(&!key));
+    }
+    assert_eq!(map.len(), keys.len());
+    assert_eq!(map.remove(&0), Some(u64::MAX));
+    assert!(!map.contains_key(&0));
+    assert_eq!(map.iter().count(), keys.len() - 1);
+
+    let mut zst = Map::<(), u8, L>::new();
+    assert!(!zst.contains_key(&()));
+    zst.insert((), 1);
+    assert_eq!(zst.insert((), 2), Some(1));
+    assert_eq!(zst.iter().collect::<Vec<_>>(), [(&(), &2)]);
+}
+
+#[test]
+fn keys_without_niche() {
+    no_niche::<SoA>();
+    no_niche::<AoS>();
+    no_niche::<CacheLine>();
+}
+
+// A few entries spread over a large table, so that iteration has to skip long empty runs
+fn sparse<L: Layout>() {
+    let mut map = Map::<u32, u32, L>::with_capacity(100_000);
+    for i in 0..100_000 {
+        map.insert(i, i);
+    }
+    map.retain(|k, _| k % 997 == 0);
+    assert!(map.capacity() > map.len() * 100);
+
+    let mut keys = map.keys().copied().collect::<Vec<_>>();
+    keys.sort_unstable();
+    assert_eq!(keys, (0..100_000).step_by(997).collect::<Vec<_>>());
+    assert_eq!(map.values_mut().count(), keys.len());
+
+    map.clear();
+    assert_eq!(map.iter().next(), None);
+    map.insert(99_999, 0);
+    assert_eq!(map.iter().collect::<Vec<_>>(), [(&99_999, &0)]);
+}
+
+#[test]
+fn sparse_iteration() {
+    sparse::<SoA>();
+    sparse::<AoS>();
+    sparse::<CacheLine>();
+}
+
+// A key that counts how many times it has been dropped
+struct Tracked(u32, Rc<Cell<usize>>);
+
+impl Hash for Tracked {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl PartialEq for Tracked {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Tracked {}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.1.set(self.1.get() + 1);
+    }
+}
+
+// Only occupied slots hold keys, so exactly the keys that were inserted are dropped, once each
+fn key_drops<L: Layout>() {
+    let drops = Rc::new(Cell::new(0));
+    let mut map = Map::<Tracked, u32, L>::new();
+    for i in 0..1000 {
+        map.insert(Tracked(i, drops.clone()), i);
+    }
+    assert_eq!(drops.get(), 0);
+
+    // A replaced entry keeps its original key and drops the new one
+    map.insert(Tracked(5, drops.clone()), 0);
+    assert_eq!(drops.get(), 1);
+
+    for i in 0..300 {
+        assert!(map.remove(&Tracked(i, drops.clone())).is_some());
+    }
+    assert_eq!(drops.get(), 1 + 300 * 2);
+
+    map.retain(|k, _| k.0 % 2 == 0);
+    assert_eq!(drops.get(), 601 + 350);
+    map.shrink_to_fit();
+    assert_eq!(drops.get(), 951);
+    drop(map);
+    assert_eq!(drops.get(), 1000 + 1 + 300);
+}
+
+#[test]
+fn drops() {
+    key_drops::<SoA>();
+    key_drops::<AoS>();
+    key_drops::<CacheLine>();
+}
@@ -0,0 +1,162 @@
+mod common;
+
+use std::hash::{BuildHasher, BuildHasherDefault};
+
+use fxhash::FxBuildHasher;
+use smash::{
+    HashMap,
+    probe::{Hopscotch, Linear, ProbeStrategy, Quadratic, RobinHood, Swiss},
+};
+
+use common::{CollidingHasher, Identity, WeakHasher};
+
+// Mixed insertions and removals over key ranges of several sizes, checked against the standard library's map
+fn churn<P: ProbeStrategy, S: BuildHasher + Default>() {
+    let mut state = 0x2545_F491_u64;
+    for &range in &[8, 64, 1000, 5000] {
+        let mut map = HashMap::<u64, u64, S, P>::new();
+        let mut reference = std::collections::HashMap::new();
+        for _ in 0..20_000 {
+            state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+            let key = (state >> 33) % range;
+            if state >> 62 == 0 {
+                assert_eq!(map.remove(&key), reference.remove(&key));
+            } else {
+                assert_eq!(map.insert(key, key + 1), reference.insert(key, key + 1));
+            }
+            assert_eq!(map.len(), reference.len());
+            assert!(map.capacity() >= map.len());
+        }
+
+        for key in 0..range {
+            assert_eq!(map.get(&key), reference.get(&key));
+        }
+        assert_eq!(map.iter().count(), reference.len());
+
+        map.retain(|k, _| k % 3 != 0);
+        reference.retain(|k, _| k % 3 != 0);
+        for key in 0..range {
+            assert_eq!(map.get(&key), reference.get(&key));
+        }
+
+        map.shrink_to_fit();
+        for key in 0..range {
+            assert_eq!(map.get(&key), reference.get(&key));
+        }
+    }
+}
+
+// Inserting and removing keys over and over must reuse or clear out the slots that they leave behind, rather than
+// growing the table. Enough keys are kept that removals never shrink it.
+fn churn_in_place<P: ProbeStrategy>() {
+    let mut map = HashMap::<u64, u64, FxBuildHasher, P>::with_capacity(16);
+    for i in 0..12 {
+        map.insert(i, i);
+    }
+    let capacity = map.capacity();
+
+    for i in 12..10_000 {
+        map.insert(i, i);
+        assert_eq!(map.remove(&i), Some(i));
+    }
+    assert_eq!(map.capacity(), capacity);
+    assert_eq!(map.len(), 12);
+    for i in 0..12 {
+        assert_eq!(map.get(&i), Some(&i));
+    }
+}
+
+// Every strategy can hold many keys that share a hash, even more than fit in a hopscotch neighbourhood
+fn colliding<P: ProbeStrategy>(len: u64) {
+    let mut map = HashMap::<u64, u64, BuildHasherDefault<CollidingHasher>, P>::new();
+    for i in 0..len {
+        map.insert(i, i);
+    }
+    for i in 0..len {
+        assert_eq!(map.get(&i), Some(&i));
+    }
+    for i in (0..len).step_by(2) {
+        assert_eq!(map.remove(&i), Some(i));
+    }
+    for i in 0..len {
+        assert_eq!(map.get(&i), if i % 2 == 0 { None } else { Some(&i) });
+    }
+}
+
+#[test]
+fn robin_hood() {
+    churn::<RobinHood, FxBuildHasher>();
+    churn::<RobinHood, BuildHasherDefault<WeakHasher>>();
+    churn_in_place::<RobinHood>();
+    colliding::<RobinHood>(100);
+}
+
+#[test]
+fn linear() {
+    churn::<Linear, FxBuildHasher>();
+    churn::<Linear, BuildHasherDefault<WeakHasher>>();
+    churn_in_place::<Linear>();
+    colliding::<Linear>(100);
+}
+
+#[test]
+fn quadratic() {
+    churn::<Quadratic, FxBuildHasher>();
+    churn::<Quadratic, BuildHasherDefault<WeakHasher>>();
+    churn_in_place::<Quadratic>();
+    colliding::<Quadratic>(100);
+}
+
+#[test]
+fn hopscotch() {
+    churn::<Hopscotch, FxBuildHasher>();
+    churn::<Hopscotch, BuildHasherDefault<WeakHasher>>();
+    churn_in_place::<Hopscotch>();
+    colliding::<Hopscotch>(100);
+}
+
+#[test]
+fn swiss() {
+    churn::<Swiss, FxBuildHasher>();
+    churn::<Swiss, BuildHasherDefault<WeakHasher>>();
+    churn_in_place::<Swiss>();
+    colliding::<Swiss>(100);
+}
+
+// Removing a key leaves a tombstone that later insertions along the same probe sequence take over
+fn tombstone_reuse<P: ProbeStrategy>() {
+    let mut map = HashMap::<u64, u64, Identity, P>::with_capacity(8);
+    let capacity = map.capacity();
+    // Keep the map from shrinking when a key is removed
+    for i in 8..13 {
+        map.insert(i, i);
+    }
+
+    // The keys that share slot 1, in slot order
+    let colliding = |map: &HashMap<u64, u64, Identity, P>| {
+        map.keys().copied().filter(|k| k % 16 == 1).collect::<Vec<_>>()
+    };
+    map.insert(1, 1);
+    map.insert(17, 17);
+    assert_eq!(colliding(&map), [1, 17]);
+
+    // Lookups continue past the tombstone
+    assert_eq!(map.remove(&1), Some(1));
+    assert_eq!(map.get(&17), Some(&17));
+
+    // And insertions take its slot, ahead of 17
+    map.insert(33, 33);
+    assert_eq!(colliding(&map), [33, 17]);
+    assert_eq!(map.get(&17), Some(&17));
+    assert_eq!(map.capacity(), capacity);
+}
+
+#[test]
+fn linear_tombstone_reuse() {
+    tombstone_reuse::<Linear>();
+}
+
+#[test]
+fn quadratic_tombstone_reuse() {
+    tombstone_reuse::<Quadratic>();
+}
@@ -0,0 +1,146 @@
+use std::hash::{BuildHasher, Hash};
+
+use fxhash::FxBuildHasher;
+use smash::{probe::Hopscotch, raw::RawTable};
+
+fn hash<T: Hash + ?Sized>(x: &T) -> u64 {
+    FxBuildHasher::default().hash_one(x)
+}
+
+#[test]
+fn insert_find_erase() {
+    let mut table = RawTable::<String>::new();
+    for i in 0..1000 {
+        let key = i.to_string();
+        table.insert(hash(&key), key, ());
+    }
+    assert_eq!(table.len(), 1000);
+
+    for i in 0..1000 {
+        let key = i.to_string();
+        let bucket = table.find(hash(&key), |k| *k == key).unwrap();
+        assert_eq!(table.get(bucket), Some((&key, &())));
+    }
+    assert_eq!(table.find(hash("x"), |k| k == "x"), None);
+
+    let buckets = table.iter().collect::<Vec<_>>();
+    assert_eq!(buckets.len(), 1000);
+    let (key, ()) = table.erase(buckets[0]).unwrap();
+    assert_eq!(table.find(hash(&key), |k| *k == key), None);
+    assert_eq!(table.len(), 999);
+
+    table.clear();
+    assert!(table.is_empty());
+    assert_eq!(table.iter().count(), 0);
+}
+
+#[test]
+fn values() {
+    let mut table = RawTable::<u64, u64>::with_capacity(4);
+    let bucket = table.insert(1, 1, 10);
+    *table.get_mut(bucket).unwrap().1 += 1;
+    assert_eq!(table.get(bucket), Some((&1, &11)));
+}
+
+#[test]
+fn reserve_and_shrink() {
+    let mut table = RawTable::<u64>::new();
+    table.reserve(100);
+    assert!(table.capacity() >= 100);
+    let cap = table.capacity();
+    for i in 0..100 {
+        table.insert(hash(&i), i, ());
+    }
+    assert_eq!(table.capacity(), cap);
+
+    table.retain(|k, _| *k % 10 == 0);
+    assert_eq!(table.len(), 10);
+    table.shrink_to(0);
+    assert!(table.capacity() >= 10 && table.capacity() < 32);
+    for i in (0..100).step_by(10) {
+        assert!(table.find(hash(&i), |k| *k == i).is_some());
+    }
+}
+
+#[test]
+fn buckets_outlive_other_entries() {
+    let mut table = RawTable::<u64>::with_capacity(16);
+    let a = table.insert(0, 0, ());
+    let b = table.insert(8, 8, ());
+
+    // Removing an entry that does not share a probe sequence moves nothing else
+    assert_eq!(table.erase(b), Some((8, ())));
+    assert!(table.is_valid(a));
+    assert_eq!(table.get(a), Some((&0, &())));
+}
+
+#[test]
+fn stale_buckets() {
+    let mut table = RawTable::<u64>::with_capacity(16);
+    let bucket = table.insert(3, 3, ());
+    assert_eq!(table.erase(bucket), Some((3, ())));
+
+    // The slot is empty, then holds a different entry
+    assert!(!table.is_valid(bucket));
+    assert_eq!(table.erase(bucket), None);
+    table.insert(3, 19, ());
+    assert!(!table.is_valid(bucket));
+    assert_eq!(table.get(bucket), None);
+
+    // An entry that is displaced to make room for another is moved
+    let mut table = RawTable::<u64>::with_capacity(16);
+    let displaced = table.insert(4, 4, ());
+    table.insert(3, 3, ());
+    table.insert(3, 19, ());
+    assert!(!table.is_valid(displaced));
+    assert_eq!(table.get(displaced), None);
+
+    // Resizing moves every entry
+    let mut table = RawTable::<u64>::with_capacity(1);
+    let bucket = table.insert(0, 0, ());
+    table.reserve(100);
+    assert!(!table.is_valid(bucket));
+}
+
+#[test]
+fn buckets_from_other_tables() {
+    let mut a = RawTable::<u64>::with_capacity(16);
+    let mut b = RawTable::<u64>::with_capacity(16);
+    let bucket = a.insert(5, 5, ());
+    assert!(!b.is_valid(bucket));
+    assert_eq!(b.get(bucket), None);
+    assert_eq!(b.erase(bucket), None);
+
+    // Even when the other table has an entry in the same slot
+    b.insert(5, 5, ());
+    assert!(!b.is_valid(bucket));
+
+    let mut clone = a.clone();
+    assert!(!clone.is_valid(bucket));
+    assert_eq!(clone.get_mut(bucket), None);
+    assert!(a.is_valid(bucket));
+
+    b.clone_from(&a);
+    assert!(!b.is_valid(bucket));
+    assert_eq!(b.len(), 1);
+}
+
+// Keys that share a hash never stop the table from making room for one more
+#[test]
+fn shared_hashes() {
+    let mut table = RawTable::<u64, (), Hopscotch>::new();
+    for i in 0..200 {
+        table.insert(7, i, ());
+    }
+    assert_eq!(table.len(), 200);
+    for i in 0..200 {
+        let bucket = table.find(7, |k| *k == i).unwrap();
+        assert_eq!(table.get(bucket), Some((&i, &())));
+    }
+    for i in (0..200).step_by(2) {
+        let bucket = table.find(7, |k| *k == i).unwrap();
+        assert_eq!(table.erase(bucket), Some((i, ())));
+    }
+    assert_eq!(table.find(7, |k| *k == 4), None);
+    assert!(table.find(7, |k| *k == 5).is_some());
+}
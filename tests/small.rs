@@ -0,0 +1,192 @@
+mod common;
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{BuildHasher, BuildHasherDefault},
+    rc::Rc,
+};
+
+use smash::SmallHashMap;
+
+use common::CollidingHasher;
+
+// Mixed insertions and removals, checked against the standard library's map
+fn churn<const N: usize>() {
+    let mut map = SmallHashMap::<u64, u64, N>::new();
+    let mut reference = std::collections::HashMap::new();
+    let mut spilled = false;
+    let mut state = 0x2545_F491_u64;
+    for _ in 0..2000 {
+        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        let key = (state >> 33) % (N as u64 + 3);
+        match state >> 62 {
+            0 => assert_eq!(map.remove(&key), reference.remove(&key)),
+            1 => assert_eq!(map.get_mut(&key), reference.get_mut(&key)),
+            _ => assert_eq!(map.insert(key, state), reference.insert(key, state)),
+        }
+        assert_eq!(map.len(), reference.len());
+        // The map spills over as soon as it holds more than `N` entries, and stays on the heap from then on
+        spilled |= reference.len() > N;
+        assert_eq!(map.is_inline(), !spilled);
+    }
+
+    for key in 0..N as u64 + 3 {
+        assert_eq!(map.get(&key), reference.get(&key));
+        assert_eq!(map.get_key_value(&key), reference.get_key_value(&key));
+    }
+    assert_eq!(map.iter().count(), reference.len());
+    for (key, val) in map.iter() {
+        assert_eq!(reference.get(key), Some(val));
+    }
+}
+
+#[test]
+fn sizes() {
+    churn::<0>();
+    churn::<1>();
+    churn::<4>();
+    churn::<8>();
+    churn::<9>();
+    churn::<32>();
+}
+
+// A hasher for maps that must never hash their keys
+#[derive(Default)]
+struct Unused;
+
+impl BuildHasher for Unused {
+    type Hasher = DefaultHasher;
+
+    fn build_hasher(&self) -> DefaultHasher {
+        panic!("a key was hashed");
+    }
+}
+
+#[test]
+fn thresholds() {
+    // Small maps only compare keys
+    let mut map = SmallHashMap::<u32, u32, 8, Unused>::new();
+    for i in 0..8 {
+        map.insert(i, i);
+    }
+    assert_eq!(map.get(&3), Some(&3));
+    assert_eq!(map.remove(&5), Some(5));
+    *map.get_or_insert_with(9, || 0) += 1;
+    assert_eq!(map.get(&9), Some(&1));
+
+    // Larger ones still find every key when all of their hashes agree
+    let mut map = SmallHashMap::<u32, u32, 16, BuildHasherDefault<CollidingHasher>>::new();
+    for i in 0..16 {
+        assert_eq!(map.insert(i, i), None);
+    }
+    assert!(map.is_inline());
+    assert_eq!(map.remove(&0), Some(0));
+    assert_eq!(map.insert(15, 30), Some(15));
+    for i in 1..15 {
+        assert_eq!(map.get(&i), Some(&i));
+    }
+    assert_eq!(map.get(&15), Some(&30));
+    assert_eq!(map.clone().get(&7), Some(&7));
+}
+
+#[test]
+fn reserve() {
+    let mut map = SmallHashMap::<u32, u32, 4>::new();
+    map.insert(1, 1);
+    map.try_reserve(3).unwrap();
+    assert!(map.is_inline());
+    map.shrink_to(4);
+    assert_eq!(map.capacity(), 4);
+
+    map.try_reserve(100).unwrap();
+    assert!(!map.is_inline());
+    assert!(map.capacity() >= 101);
+    map.shrink_to(10);
+    assert!(map.capacity() >= 10 && map.capacity() < 101);
+    assert_eq!(map.get(&1), Some(&1));
+}
+
+#[test]
+fn spill() {
+    let mut map = SmallHashMap::<u32, u32, 4>::new();
+    for i in 0..4 {
+        map.insert(i, i);
+    }
+    assert!(map.is_inline());
+    assert!(map.capacity() >= 4);
+
+    // Replacing a value or removing a key makes room without spilling
+    assert_eq!(map.insert(2, 20), Some(2));
+    assert_eq!(map.remove(&0), Some(0));
+    map.insert(10, 10);
+    assert!(map.is_inline());
+
+    map.insert(11, 11);
+    assert!(!map.is_inline());
+    assert_eq!(map.len(), 5);
+    for (key, val) in [(1, 1), (2, 20), (3, 3), (10, 10), (11, 11)] {
+        assert_eq!(map.get(&key), Some(&val));
+    }
+
+    assert!(SmallHashMap::<u32, u32, 4>::with_capacity(4).is_inline());
+    assert!(!SmallHashMap::<u32, u32, 4>::with_capacity(5).is_inline());
+}
+
+#[test]
+fn drops() {
+    let rc = Rc::new(());
+    let mut map = SmallHashMap::<u32, Rc<()>, 4>::new();
+    for i in 0..4 {
+        map.insert(i, rc.clone());
+    }
+    assert!(map.insert(2, rc.clone()).is_some());
+    assert_eq!(Rc::strong_count(&rc), 5);
+    assert!(map.remove(&0).is_some());
+    assert_eq!(Rc::strong_count(&rc), 4);
+
+    let clone = map.clone();
+    assert_eq!(Rc::strong_count(&rc), 7);
+    drop(clone);
+
+    map.retain(|k, _| *k != 1);
+    assert_eq!(Rc::strong_count(&rc), 3);
+    map.clear();
+    assert_eq!(Rc::strong_count(&rc), 1);
+
+    // And once spilled over
+    for i in 0..10 {
+        map.insert(i, rc.clone());
+    }
+    let clone = map.clone();
+    assert_eq!(Rc::strong_count(&rc), 21);
+    drop(clone);
+    drop(map);
+    assert_eq!(Rc::strong_count(&rc), 1);
+}
+
+#[test]
+fn get_or_insert_with() {
+    let mut map = SmallHashMap::<u32, u32, 8>::new();
+    for i in 0..8 {
+        *map.get_or_insert_with(i % 5, || 0) += 1;
+    }
+    assert_eq!(map.len(), 5);
+    assert!(map.is_inline());
+
+    map.retain(|k, _| k % 2 == 0);
+    assert_eq!(map.keys().count(), 3);
+    for val in map.values_mut() {
+        *val = 0;
+    }
+    assert!(map.values().all(|val| *val == 0));
+
+    // Spilling over part way through
+    for i in 0..100 {
+        *map.get_or_insert_with(i, || 7) += 1;
+    }
+    assert!(!map.is_inline());
+    assert_eq!(map.len(), 100);
+    for (key, val) in map.iter_mut() {
+        assert_eq!(*val, if *key <= 4 && key % 2 == 0 { 1 } else { 8 });
+    }
+}
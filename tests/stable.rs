@@ -0,0 +1,107 @@
+use std::{marker::PhantomPinned, pin::Pin, rc::Rc};
+
+use smash::StableHashMap;
+
+#[test]
+fn values_never_move() {
+    let mut map = StableHashMap::<u32, String>::new();
+    map.insert(0, "zero".to_string());
+    let addr = map.get(&0).unwrap() as *const String;
+    for i in 1..10_000 {
+        map.insert(i, i.to_string());
+    }
+    assert_eq!(map.get(&0).unwrap() as *const String, addr);
+
+    // A replacement takes over the old value's address
+    assert_eq!(map.insert(0, "again".to_string()), Some("zero".to_string()));
+    assert_eq!(map.get(&0).unwrap() as *const String, addr);
+    map.get_mut(&0).unwrap().push('!');
+    assert_eq!(map.get_key_value(&0), Some((&0, &"again!".to_string())));
+
+    // Removing and reinserting other values leaves the survivors in place
+    let addrs = (5000..10_000).map(|i| map.get(&i).unwrap() as *const String).collect::<Vec<_>>();
+    for i in 0..5000 {
+        assert!(map.remove(&i).is_some());
+    }
+    for i in 10_000..15_000 {
+        map.insert(i, i.to_string());
+    }
+    for (i, addr) in (5000..10_000).zip(addrs) {
+        assert_eq!(map.get(&i), Some(&i.to_string()));
+        assert_eq!(map.get(&i).unwrap() as *const String, addr);
+    }
+    assert_eq!(map.len(), 10_000);
+    assert_eq!(map.iter().count(), 10_000);
+    assert_eq!(map.keys().count(), map.values().count());
+
+    assert_eq!(map.remove_entry(&5000), Some((5000, "5000".to_string())));
+    assert!(!map.contains_key(&5000));
+}
+
+// A value that must not move once pinned
+struct Unmovable {
+    val: u32,
+    _pinned: PhantomPinned,
+}
+
+impl Unmovable {
+    fn new(val: u32) -> Self {
+        Self { val, _pinned: PhantomPinned }
+    }
+}
+
+#[test]
+fn pinned() {
+    let mut map = StableHashMap::<u32, Unmovable>::new();
+    let mut addrs = Vec::new();
+    for i in 0..1000 {
+        let pinned = map.insert_pinned(i, Unmovable::new(i));
+        addrs.push(&*pinned as *const Unmovable);
+    }
+    for i in 0..1000 {
+        let pinned: Pin<&mut Unmovable> = map.get_pinned(&i).unwrap();
+        assert_eq!(pinned.val, i);
+        assert_eq!(&*pinned as *const Unmovable, addrs[i as usize]);
+    }
+
+    // Replacing a pinned value drops the old one where it is
+    let pinned = map.insert_pinned(7, Unmovable::new(70));
+    assert_eq!(&*pinned as *const Unmovable, addrs[7]);
+    assert_eq!(map.get(&7).unwrap().val, 70);
+
+    // It keeps its address even when other values have left their slots free for reuse
+    assert!(map.delete(&3));
+    let pinned = map.insert_pinned(9, Unmovable::new(90));
+    assert_eq!(&*pinned as *const Unmovable, addrs[9]);
+    assert_eq!(map.get_pinned(&9).map(|pinned| &*pinned as *const Unmovable), Some(addrs[9]));
+
+    assert!(map.delete(&7));
+    assert!(!map.delete(&7));
+    assert!(map.get_pinned(&7).is_none());
+    assert_eq!(map.len(), 998);
+}
+
+#[test]
+fn drops() {
+    let rc = Rc::new(());
+    let mut map = StableHashMap::<u32, Rc<()>>::new();
+    for i in 0..300 {
+        map.insert_pinned(i, rc.clone());
+    }
+    map.insert_pinned(5, rc.clone());
+    assert_eq!(Rc::strong_count(&rc), 301);
+    for i in 0..100 {
+        assert!(map.delete(&i));
+    }
+    assert_eq!(Rc::strong_count(&rc), 201);
+
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(Rc::strong_count(&rc), 1);
+
+    for i in 0..100 {
+        map.insert(i, rc.clone());
+    }
+    drop(map);
+    assert_eq!(Rc::strong_count(&rc), 1);
+}
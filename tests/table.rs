@@ -0,0 +1,132 @@
+use std::hash::{Hash, Hasher};
+
+use fxhash::FxHasher;
+use smash::{
+    HashTable,
+    table::Entry,
+};
+
+// Names that are equal regardless of case
+fn hash_name(name: &str) -> u64 {
+    let mut hasher = FxHasher::default();
+    name.to_lowercase().hash(&mut hasher);
+    hasher.finish()
+}
+
+fn named(name: &str) -> impl Fn(&(String, u32)) -> bool + '_ {
+    move |entry| entry.0.eq_ignore_ascii_case(name)
+}
+
+#[test]
+fn projected_keys() {
+    let mut table = HashTable::<(String, u32)>::new();
+    for (i, name) in ["Foo", "BAR", "foo", "Bar", "baz"].iter().enumerate() {
+        table
+            .entry(hash_name(name), named(name))
+            .and_modify(|(_, count)| *count += 1)
+            .or_insert_with(|| (name.to_string(), i as u32));
+    }
+    assert_eq!(table.len(), 3);
+    assert_eq!(table.find(hash_name("FOO"), named("FOO")), Some(&("Foo".to_string(), 1)));
+    assert_eq!(table.find(hash_name("qux"), named("qux")), None);
+
+    table.find_mut(hash_name("baz"), named("baz")).unwrap().1 = 10;
+    assert_eq!(table.remove(hash_name("bar"), named("bar")), Some(("BAR".to_string(), 2)));
+    assert_eq!(table.remove(hash_name("bar"), named("bar")), None);
+
+    // Unique insertion does not look for an equal value
+    table.insert_unique(hash_name("baz"), ("BAZ".to_string(), 100));
+    assert_eq!(table.len(), 3);
+    table.retain(|(name, _)| name != "baz");
+    assert_eq!(table.len(), 2);
+    assert_eq!(table.iter().count(), 2);
+    assert_eq!(table.find(hash_name("baz"), named("baz")), Some(&("BAZ".to_string(), 100)));
+
+    for entry in table.iter_mut() {
+        entry.1 += 1;
+    }
+    assert_eq!(table.find(hash_name("baz"), named("baz")), Some(&("BAZ".to_string(), 101)));
+    assert_eq!(table.find(hash_name("foo"), named("foo")), Some(&("Foo".to_string(), 2)));
+
+    table.clear();
+    assert!(table.is_empty());
+    assert_eq!(table.iter().count(), 0);
+}
+
+// Mixed operations through the entry API, with hashes shared by groups of three keys, checked against the standard
+// library's map
+#[test]
+fn entries() {
+    fn hash(key: u32) -> u64 {
+        ((key / 3) as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+
+    let mut table = HashTable::<(u32, u64)>::new();
+    let mut reference = std::collections::HashMap::new();
+    let mut state = 0x2545_F491_u64;
+    for _ in 0..100_000 {
+        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        let key = ((state >> 33) % 3000) as u32;
+        match table.entry(hash(key), |entry| entry.0 == key) {
+            Entry::Occupied(entry) if state >> 62 == 0 => {
+                assert_eq!(Some(entry.remove().1), reference.remove(&key));
+            },
+            Entry::Occupied(mut entry) => {
+                assert_eq!(Some(entry.insert((key, state)).1), reference.insert(key, state));
+            },
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.insert((key, state)), &(key, state));
+                assert_eq!(reference.insert(key, state), None);
+            },
+        }
+        assert_eq!(table.len(), reference.len());
+    }
+
+    let clone = table.clone();
+    for key in 0..3000 {
+        let found = clone.find(hash(key), |entry| entry.0 == key).map(|entry| entry.1);
+        assert_eq!(found, reference.get(&key).copied());
+    }
+
+    table.retain(|entry| entry.0 % 2 == 0);
+    table.shrink_to_fit();
+    assert!(table.capacity() >= table.len());
+    for key in 0..3000 {
+        let found = table.find(hash(key), |entry| entry.0 == key).map(|entry| entry.1);
+        assert_eq!(found, reference.get(&key).copied().filter(|_| key % 2 == 0));
+    }
+}
+
+#[test]
+fn reserve() {
+    let mut table = HashTable::with_capacity(0);
+    table.reserve(100);
+    let capacity = table.capacity();
+    assert!(capacity >= 100);
+    for key in 0..100 {
+        table.insert_unique(key, key);
+    }
+    assert_eq!(table.capacity(), capacity);
+    for key in 0..100 {
+        assert_eq!(table.find(key, |k| *k == key), Some(&key));
+    }
+}
+
+// Finding a value that is already present never grows the table, even when it is full
+#[test]
+fn entry_when_full() {
+    let mut table = HashTable::with_capacity(4);
+    for key in 0..4u64 {
+        table.insert_unique(key, key);
+    }
+    let capacity = table.capacity();
+    assert_eq!(table.len(), capacity);
+
+    assert!(matches!(table.entry(2, |k| *k == 2), Entry::Occupied(_)));
+    assert_eq!(table.capacity(), capacity);
+    if let Entry::Vacant(entry) = table.entry(4, |k| *k == 4) {
+        entry.insert(4);
+    }
+    assert!(table.capacity() > capacity);
+    assert!((0..5).all(|key| table.find(key, |k| *k == key) == Some(&key)));
+}